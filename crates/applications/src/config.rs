@@ -0,0 +1,78 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    /// Also match the Desktop Actions of the entries, e.g. "New Private Window".
+    desktop_actions: Option<bool>,
+    /// Typed between an entry and the files or URLs to open with it, e.g. "editor -- notes.md".
+    args_separator: Option<String>,
+    /// The maximum number of matches.
+    max_entries: Option<usize>,
+    // TODO 4.
+    // terminal: Option<String>,
+    // shell: Option<String>,
+    /// What is shown on a blank input.
+    bib: Option<common::Bib>,
+    /// How the input is matched against the titles.
+    matching: Option<common::MatchingConfig>,
+}
+
+impl common::SourceConfig for Config {
+    fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("")
+    }
+    fn bib(&self) -> &common::Bib {
+        self.bib.as_ref().unwrap_or(&common::Bib::None)
+    }
+    fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(5)
+    }
+    fn matcher(&self) -> common::Matcher {
+        self.matching.clone().unwrap_or_default().matcher()
+    }
+}
+
+// QoL methods:
+impl Config {
+    pub(crate) fn desktop_actions(&self) -> bool {
+        self.desktop_actions.unwrap_or(false)
+    }
+    pub(crate) fn args_separator(&self) -> &str {
+        self.args_separator.as_deref().unwrap_or("--")
+    }
+    // fn terminal(&self) -> &str {
+    //     self.terminal.as_deref().unwrap_or("kitty")
+    // }
+    // fn shell(&self) -> String {
+    //     self.shell.clone().unwrap_or_else(|| {
+    //         env::var("SHELL").unwrap_or_else(|e| {
+    //             eprintln!("(Applications) Failed while finding the SHELL env variable: {e}");
+    //             process::exit(1);
+    //         })
+    //     })
+    // }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // let shell = env::var("SHELL").unwrap_or_else(|e| {
+        //     eprintln!(
+        //         "(Applications) Failed while getting the SHELL env variable. Closing...:\n  {e}"
+        //     );
+        //     process::exit(1);
+        // });
+        Config {
+            prefix: Some(String::default()),
+            desktop_actions: Some(false),
+            args_separator: Some("--".to_string()),
+            max_entries: Some(5),
+            // terminal: Some("kitty".to_string()),
+            // shell: Some(shell),
+            bib: Some(common::Bib::None),
+            matching: Some(common::MatchingConfig::default()),
+        }
+    }
+}
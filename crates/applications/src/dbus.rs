@@ -0,0 +1,95 @@
+use common::launch::Startup;
+use freedesktop_desktop_entry::DesktopEntry;
+use std::{collections::HashMap, env, error::Error, path::Path};
+use zbus::{blocking::Connection, zvariant::Value};
+
+const INTERFACE: &str = "org.freedesktop.Application";
+
+pub fn is_dbus_activatable(de: &DesktopEntry) -> bool {
+    de.desktop_entry("DBusActivatable") == Some("true")
+}
+
+// The object path is derived from the bus name (which is the desktop file ID) by
+// replacing dots with slashes and dashes with underscores, as the spec says:
+fn object_path(appid: &str) -> String {
+    format!("/{}", appid.replace('.', "/").replace('-', "_"))
+}
+
+// Activation tokens are passed through the platform data instead of the environment:
+fn platform_data(startup: &Startup) -> HashMap<&'static str, Value<'static>> {
+    startup
+        .env()
+        .into_iter()
+        .map(|(var, token)| match var {
+            "XDG_ACTIVATION_TOKEN" => ("activation-token", Value::from(token)),
+            _ => ("desktop-startup-id", Value::from(token)),
+        })
+        .collect()
+}
+
+// `Open` only takes URIs, so the paths of files are turned into file:// URIs:
+fn uri(arg: &str) -> String {
+    if arg.contains("://") {
+        return arg.to_string();
+    }
+    let path = Path::new(arg);
+    let path = match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// Activates the application through `org.freedesktop.Application` on the session bus.
+/// `Activate` is used for plain launches, `ActivateAction` for desktop actions and `Open`
+/// when there are files or URLs to open.
+pub fn activate(
+    appid: &str,
+    action: Option<&str>,
+    args: &[String],
+    startup: &Startup,
+) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::session()
+        .map_err(|e| format!("Failed while connecting to the session bus:\n    {e}"))?;
+    let path = object_path(appid);
+    let platform_data = platform_data(startup);
+
+    match (action, args) {
+        (Some(action), _) => conn.call_method(
+            Some(appid),
+            path.as_str(),
+            Some(INTERFACE),
+            "ActivateAction",
+            &(action, Vec::<Value>::new(), platform_data),
+        ),
+        (None, []) => conn.call_method(
+            Some(appid),
+            path.as_str(),
+            Some(INTERFACE),
+            "Activate",
+            &(platform_data,),
+        ),
+        (None, args) => {
+            let uris: Vec<String> = args.iter().map(|arg| uri(arg)).collect();
+            conn.call_method(
+                Some(appid),
+                path.as_str(),
+                Some(INTERFACE),
+                "Open",
+                &(uris, platform_data),
+            )
+        }
+    }
+    .map_err(|e| format!("Failed while calling {INTERFACE} on {appid}:\n    {e}"))?;
+
+    Ok(())
+}
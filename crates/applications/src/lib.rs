@@ -1,78 +1,23 @@
-use abi_stable::std_types::{
-    ROption::{RNone, RSome},
-    RString, RVec,
-};
+use abi_stable::std_types::{RString, RVec};
 use anyrun_plugin::*;
+use common::{launch, SourceConfig};
 use freedesktop_desktop_entry::DesktopEntry;
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use serde::Deserialize;
-use std::
-    process::{self, Command}
-;
+use std::borrow::Cow;
 
+mod config;
+mod dbus;
 mod util;
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
-    // TODO 5.
-    // desktop_actions: Option<bool>,
-    max_entries: Option<usize>,
-    // TODO 4.
-    // terminal: Option<String>,
-    // shell: Option<String>,
-    bib: Option<common::Bib>,
-}
-
-// QoL methods:
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or("")
-    }
-    fn bib(&self) -> &common::Bib {
-        self.bib.as_ref().unwrap_or(&common::Bib::None)
-    }
-    fn max_entries(&self) -> usize {
-        self.max_entries.unwrap_or(5)
-    }
-    // fn terminal(&self) -> &str {
-    //     self.terminal.as_deref().unwrap_or("kitty")
-    // }
-    // fn shell(&self) -> String {
-    //     self.shell.clone().unwrap_or_else(|| {
-    //         env::var("SHELL").unwrap_or_else(|e| {
-    //             eprintln!("(Applications) Failed while finding the SHELL env variable: {e}");
-    //             process::exit(1);
-    //         })
-    //     })
-    // }
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        // let shell = env::var("SHELL").unwrap_or_else(|e| {
-        //     eprintln!(
-        //         "(Applications) Failed while getting the SHELL env variable. Closing...:\n  {e}"
-        //     );
-        //     process::exit(1);
-        // });
-        Config {
-            prefix: Some(String::default()),
-            // desktop_actions: Some(false),
-            max_entries: Some(5),
-            // terminal: Some("kitty".to_string()),
-            // shell: Some(shell),
-            bib: Some(common::Bib::None),
-        }
-    }
-}
+use config::Config;
 
 pub struct InitData<'a> {
-    config: Config,
-    common_config: common::CommonConfig,
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
     // I am not sure if this is supposed to be like this, but the idea of mapping
     // paths to DesktopEntry types in the get_matches() function sounds absurd.
-    entries: Vec<DesktopEntry<'a>>,
+    entries: common::Fallible<Vec<DesktopEntry<'a>>>,
+    // The files or URLs typed after the separator, for the entry which gets selected:
+    args: Vec<String>,
 }
 
 #[info]
@@ -87,149 +32,265 @@ fn info() -> PluginInfo {
 pub fn init(config_dir: RString) -> InitData<'static> {
     let config = common::config(&config_dir, "Applications");
     let common_config = common::common_config(&config_dir, "Applications");
-    let entries = util::scrape_desktop_entries().unwrap_or_else(|e| {
-        eprintln!("(Applications) Failed to load desktop entries. Closing...:\n  {e}");
-        process::exit(1)
-    });
+    let entries = common::Fallible::new(
+        "Applications",
+        &config_dir,
+        util::scrape_desktop_entries(),
+    );
 
     InitData {
         config,
         entries,
         common_config,
+        args: Vec::new(),
     }
 }
 
 #[get_matches]
-fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
+fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    data.config.reload();
+    data.common_config.reload();
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
+    }
+    matches
+}
+
+fn matches(input: RString, data: &mut InitData) -> RVec<Match> {
     let InitData {
         config,
         entries,
         common_config: _,
+        args,
     } = data;
 
-    // Early return for the wrong prefix:
+    // Early return for the wrong prefix, so the entries aren't retried for every input:
     if !input.starts_with(config.prefix()) {
         return RVec::new();
     }
+    let (input, typed_args) = split_args(&input, config.args_separator());
+    *args = typed_args;
 
-    // It is safe to unwrap here because of the early return above.
-    let stripped_input = input.strip_prefix(config.prefix()).unwrap();
-
-    // Handling blank input behaviour:
-    if stripped_input.is_empty() {
-        match config.bib() {
-            common::Bib::All => {
-                return RVec::from_iter(entries.iter().take(config.max_entries()).map(|de| Match {
-                    title: RString::from(de.name::<&str>(&[]).unwrap_or("Desktop Entry".into())),
-                    description: RSome(RString::from(de.comment::<&str>(&[]).unwrap_or_default())),
-                    use_pango: false,
-                    icon: RSome(RString::from(
-                        de.icon().unwrap_or("application-x-executable"),
-                    )),
-                    id: RNone,
-                }))
-            }
-            common::Bib::None => return RVec::new(),
-            common::Bib::Currated(v) => {
-                return RVec::from_iter(
-                    entries
-                        .iter()
-                        .filter(|de| v.contains(&de.appid.to_string()))
-                        .take(config.max_entries())
-                        .map(|de| Match {
-                            title: RString::from(
-                                de.name::<&str>(&[]).unwrap_or("Desktop Entry".into()),
-                            ),
-                            description: RSome(RString::from(
-                                de.comment::<&str>(&[]).unwrap_or_default(),
-                            )),
-                            use_pango: false,
-                            icon: RSome(RString::from(
-                                de.icon().unwrap_or("application-x-executable"),
-                            )),
-                            id: RNone,
-                        }),
-                )
-            }
+    let entries = match entries.get_or_retry(util::scrape_desktop_entries) {
+        Ok(entries) => &*entries,
+        Err(diagnostic) => return RVec::from(vec![*diagnostic]),
+    };
+
+    common::source::matches(&Entries::new(entries, config, args), &**config, input)
+}
+
+// Splits "editor -- notes.md" into the input which is matched and the files or URLs to
+// open. The separator only counts as a word of its own:
+fn split_args<'a>(input: &'a str, separator: &str) -> (&'a str, Vec<String>) {
+    if separator.is_empty() {
+        return (input, Vec::new());
+    }
+    let found = input.match_indices(separator).find(|&(i, _)| {
+        let before = input[..i].chars().next_back();
+        let after = input[i + separator.len()..].chars().next();
+        before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+    });
+    match found {
+        Some((i, _)) => {
+            let args = input[i + separator.len()..]
+                .split_whitespace()
+                .map(common::expand)
+                .collect();
+            (&input[..i], args)
         }
+        None => (input, Vec::new()),
     }
+}
 
-    let matcher = SkimMatcherV2::default();
-    // Shadowing "entries"; performing fuzzy matching:
-    let mut entries: Vec<(i64, &DesktopEntry)> = entries
-        .iter()
-        .filter_map(|de| {
-            let score =
-                matcher.fuzzy_match(de.name::<&str>(&[])?.into_owned().as_str(), stripped_input)?;
-            Some((score, de))
-        })
-        .collect();
-    // Sorting entries by score in descending order.
-    entries.sort_by(|a, b| b.0.cmp(&a.0));
-
-    RVec::from_iter(
-        entries
+// Only entries with a field code for files or URLs in their Exec can open anything:
+fn takes_args(de: &DesktopEntry) -> bool {
+    de.exec().is_some_and(|exec| {
+        ["%f", "%F", "%u", "%U"]
             .iter()
-            .take(config.max_entries())
-            .map(|(_, de)| Match {
-                title: RString::from(de.name::<&str>(&[]).unwrap_or("Desktop Entry".into())),
-                description: RSome(RString::from(de.comment::<&str>(&[]).unwrap_or_default())),
-                use_pango: false,
-                icon: RSome(RString::from(de.icon().unwrap_or_default())),
-                id: RNone,
-            }),
-    )
+            .any(|code| exec.contains(code))
+    })
+}
+
+// The Desktop Entries and, if enabled, their Desktop Actions, as one list of items. The
+// entries come first, so a blank input still shows entries only. With files or URLs to
+// open, only the entries which can open them are left:
+struct Entries<'a, 'b> {
+    entries: &'a [DesktopEntry<'b>],
+    items: Vec<(usize, Option<usize>)>,
+    args: &'a [String],
+}
+
+impl<'a, 'b> Entries<'a, 'b> {
+    fn new(entries: &'a [DesktopEntry<'b>], config: &Config, args: &'a [String]) -> Self {
+        if !args.is_empty() {
+            let items = (0..entries.len())
+                .filter(|&i| takes_args(&entries[i]))
+                .map(|i| (i, None))
+                .collect();
+            return Entries {
+                entries,
+                items,
+                args,
+            };
+        }
+
+        let mut items: Vec<(usize, Option<usize>)> = (0..entries.len()).map(|i| (i, None)).collect();
+        if config.desktop_actions() {
+            for (i, de) in entries.iter().enumerate() {
+                // Actions without a name can't be shown, so they are left out:
+                items.extend(
+                    actions(de)
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(_, action)| de.action_entry(action, "Name").is_some())
+                        .map(|(a, _)| (i, Some(a))),
+                );
+            }
+        }
+        Entries {
+            entries,
+            items,
+            args,
+        }
+    }
+}
+
+fn actions<'a>(de: &'a DesktopEntry) -> Vec<&'a str> {
+    de.desktop_entry("Actions")
+        .unwrap_or_default()
+        .split(';')
+        .filter(|action| !action.is_empty())
+        .collect()
+}
+
+impl common::ItemSource for Entries<'_, '_> {
+    type Item = (usize, Option<usize>);
+
+    fn items(&self) -> &[Self::Item] {
+        &self.items
+    }
+
+    fn title<'a>(&'a self, &(entry, action): &'a Self::Item) -> Cow<'a, str> {
+        let de = &self.entries[entry];
+        let name = de.name::<&str>(&[]).unwrap_or("Desktop Entry".into());
+        match action.and_then(|a| de.action_entry(actions(de)[a], "Name")) {
+            Some(action_name) => Cow::Owned(format!("{name}: {action_name}")),
+            None => name,
+        }
+    }
+
+    fn description<'a>(&'a self, &(entry, _): &'a Self::Item) -> Option<Cow<'a, str>> {
+        Some(self.entries[entry].comment::<&str>(&[]).unwrap_or_default())
+    }
+
+    fn icon<'a>(&'a self, &(entry, action): &'a Self::Item) -> Option<Cow<'a, str>> {
+        let de = &self.entries[entry];
+        let icon = action
+            .and_then(|a| de.action_entry(actions(de)[a], "Icon"))
+            .or(de.icon())
+            .unwrap_or("application-x-executable");
+        Some(Cow::Borrowed(icon))
+    }
+
+    // Entries are curated by their ID, e.g. "firefox", and actions by the ID and the
+    // action, e.g. "firefox:new-private-window":
+    fn key<'a>(&'a self, &(entry, action): &'a Self::Item) -> Cow<'a, str> {
+        let de = &self.entries[entry];
+        match action {
+            Some(a) => Cow::Owned(format!("{}:{}", de.appid, actions(de)[a])),
+            None => Cow::Borrowed(&de.appid),
+        }
+    }
+
+    fn activate(
+        &self,
+        &(entry, action): &Self::Item,
+        common_config: &common::CommonConfig,
+    ) -> HandleResult {
+        let selected_de = &self.entries[entry];
+        let action = action.and_then(|a| actions(selected_de).get(a).copied());
+        let startup = launch::Startup::from_desktop_entry(selected_de);
+
+        // Entries which are DBusActivatable have to be started through D-Bus and Exec is only a fallback:
+        if dbus::is_dbus_activatable(selected_de) {
+            match dbus::activate(&selected_de.appid, action, self.args, &startup) {
+                Ok(()) => return HandleResult::Close,
+                Err(e) => eprintln!(
+                    "(Applications) Failed while activating the Desktop Entry through D-Bus. \
+                    Falling back to Exec...\n  {e}"
+                ),
+            }
+        }
+
+        let uris: Vec<&str> = self.args.iter().map(String::as_str).collect();
+        let exec = match action {
+            Some(action) => selected_de.parse_exec_action(action),
+            None if !uris.is_empty() => selected_de.parse_exec_with_uris(&uris, &[] as &[&str]),
+            None => selected_de.parse_exec(),
+        };
+        let exec = match exec {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("(Applications) Failed while parsing exec from selected Desktop Entry. Closing...\n  {e}.");
+                return HandleResult::Close;
+            }
+        };
+
+        // It is safe to unwrap here because "parse_exec()" never returns an empty argv.
+        common_config
+            .launcher(&exec[0])
+            .args(&exec[1..])
+            .current_dir(selected_de.desktop_entry("Path"))
+            .startup(startup)
+            .spawn()
+            .unwrap_or_else(|e| eprintln!("(Applications) Failed while executing command:\n  {e}"));
+
+        // if selected_de.terminal() {
+        //     if let Err(e) = Command::new().args(["-e", &exec]).spawn() {
+        //         eprintln!(
+        //             "(Applications) Failed while executing Desktop Entry's exec using the terminal emulator:\n  {e}"
+        //         )
+        //     }
+        // } else {
+        //     if let Err(e) = Command::new(config.shell()).args(["-c", &exec]).spawn() {
+        //         eprintln!("(Applications) Failed while executing Desktop Entry's exec using the shell:\n  {e}")
+        //     }
+        // }
+
+        HandleResult::Close
+    }
 }
 
 #[handler]
 pub fn handler(selection: Match, data: &InitData) -> HandleResult {
     let InitData {
-        config: _,
+        config,
         entries,
         common_config,
+        args,
     } = data;
-    let selected_de = entries
-        .iter()
-        .find(|de| de.name::<&str>(&[]).unwrap() == selection.title)
-        // It is safe to unwrap the ".find()" method because there is no
-        // way selection is not in the "entries" vector.
-        .unwrap();
-
-    let exec = match selected_de.parse_exec() {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("(Applications) Failed while parsing exec from selected Desktop Entry. Closing...\n  {e}.");
-            return HandleResult::Close;
-        }
-    };
 
-    if common_config.prefix_args().is_empty() {
-        match Command::new(&exec[0]).args(&exec[1..]).spawn() {
-            Ok(_) => (),
-            Err(e) => eprintln!("(Applications) Failed while executing command:\n  {e}"),
-        }
-    } else {
-        match Command::new(&common_config.prefix_args()[0])
-            .args(common_config.prefix_args()[1..].iter())
-            .args(exec.iter())
-            .spawn()
-        {
-            Ok(_) => (),
-            Err(e) => eprintln!("(Applications) Failed while executing command:\n  {e}"),
-        }
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
     }
 
-    // if selected_de.terminal() {
-    //     if let Err(e) = Command::new().args(["-e", &exec]).spawn() {
-    //         eprintln!(
-    //             "(Applications) Failed while executing Desktop Entry's exec using the terminal emulator:\n  {e}"
-    //         )
-    //     }
-    // } else {
-    //     if let Err(e) = Command::new(config.shell()).args(["-c", &exec]).spawn() {
-    //         eprintln!("(Applications) Failed while executing Desktop Entry's exec using the shell:\n  {e}")
-    //     }
-    // }
+    if let Some(result) = entries.handle(&selection, common_config) {
+        return result;
+    }
+    // It is safe to unwrap here, because only the diagnostic match can be
+    // selected while the entries are failing to load.
+    let entries = entries.get().unwrap();
 
-    HandleResult::Close
+    common::source::handle(
+        &Entries::new(entries, config, args),
+        &selection,
+        common_config,
+    )
 }
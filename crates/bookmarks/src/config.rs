@@ -0,0 +1,51 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    // It has to be usize because the .take() method takes usize...
+    /// The maximum number of matches.
+    max_entries: Option<usize>,
+    /// What is shown on a blank input.
+    bib: Option<common::Bib>,
+    /// How the input is matched against the titles.
+    matching: Option<common::MatchingConfig>,
+    /// Overrides the browser from Common.ron, e.g. to read the bookmarks of another profile.
+    browser: Option<common::BrowserConfig>,
+}
+
+impl common::SourceConfig for Config {
+    fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("*")
+    }
+    fn bib(&self) -> &common::Bib {
+        self.bib.as_ref().unwrap_or(&common::Bib::All)
+    }
+    fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(7)
+    }
+    fn matcher(&self) -> common::Matcher {
+        self.matching.clone().unwrap_or_default().matcher()
+    }
+}
+
+// QoL methods so I don't have to chain methods:
+impl Config {
+    pub(crate) fn browser(&self) -> Option<&common::BrowserConfig> {
+        self.browser.as_ref()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some("*".to_string()),
+            max_entries: Some(7),
+            bib: Some(common::Bib::All),
+            matching: Some(common::MatchingConfig::default()),
+            browser: None,
+        }
+    }
+}
@@ -64,7 +64,8 @@ impl Bookmarks for common::Firefox {
 
         // MAIN
         // Creating the connection:
-        let profile_dir = common::Firefox::profile_dir2(&self)
+        let profile_dir = self
+            .profile_dir()
             .map_err(|e| format!("Failed while getting Firefox profile directory:\n    {e}"))?;
         let conn = Connection::open(format!("{profile_dir}/places.sqlite"))
             .map_err(|e| format!("Failed while creating the DB connection:\n    {e}"))?;
@@ -1,14 +1,14 @@
-use abi_stable::std_types::{
-    ROption::{RNone, RSome},
-    RString, RVec,
-};
+use abi_stable::std_types::{ROption::RSome, RString, RVec};
 use anyrun_plugin::*;
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use serde::{Deserialize, Serialize};
-use std::{error::Error, process};
+use common::SourceConfig;
+use std::{borrow::Cow, error::Error, slice};
 
+mod config;
 mod firefox;
 
+use config::Config;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Bookmark {
     title: String,
@@ -27,75 +27,55 @@ struct Bookmark {
 // }
 
 trait Bookmarks: common::Browser {
-    // I suspect every single browser would need the profile name as a parameter...
     fn bookmarks(&self) -> Result<Vec<Bookmark>, Box<dyn Error>>;
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Config {
-    prefix: Option<String>,
-    // It has to be usize because the .take() method takes usize...
-    max_entries: Option<usize>,
-    bib: Option<common::Bib>,
+// Everything that can fail to load. It is kept separate from the config so the plugin
+// can keep running (and retry) when the browser or its bookmarks are unavailable:
+struct Loaded {
+    browser: Box<dyn Bookmarks>,
+    bookmarks: Vec<Bookmark>,
+    // How the last query wants its bookmark opened, for the handler, which only gets the match:
+    open_mode: common::OpenMode,
 }
 
-// QoL methods so I don't have to chain methods:
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or("*")
-    }
-    fn max_entries(&self) -> usize {
-        self.max_entries.unwrap_or(7)
-    }
-    fn bib(&self) -> &common::Bib {
-        self.bib.as_ref().unwrap_or(&common::Bib::None)
-    }
+// This exists so I don't have to call util::get_default_browser() in get_matches() AND in handle():
+struct InitData {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+    loaded: common::Fallible<Loaded>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some("*".to_string()),
-            max_entries: Some(7),
-            bib: Some(common::Bib::All),
+fn load(config: &Config, common_config: &common::CommonConfig) -> Result<Loaded, Box<dyn Error>> {
+    let known_browser = common::browser(common_config, config.browser())
+        .map_err(|e| format!("Failed while getting the browser:\n    {e}"))?;
+    let browser: Box<dyn Bookmarks> = match known_browser {
+        common::KnownBrowser::Firefox(firefox) => Box::new(firefox),
+        common::KnownBrowser::Chromium(_) => {
+            return Err("Bookmarks can only be read from Firefox so far!".into())
         }
-    }
-}
+    };
+    let bookmarks = browser
+        .bookmarks()
+        .map_err(|e| format!("Failed while getting bookmarks:\n    {e}"))?;
 
-// This exists so I don't have to call util::get_default_browser() in get_matches() AND in handle():
-struct InitData {
-    config: Config,
-    common_config: common::CommonConfig,
-    browser: Box<dyn Bookmarks>,
-    bookmarks: Vec<Bookmark>,
+    Ok(Loaded {
+        browser,
+        bookmarks,
+        open_mode: common::OpenMode::default(),
+    })
 }
 
 #[init]
 fn init(config_dir: RString) -> InitData {
-    let config = common::config(&config_dir, "Bookmarks");
+    let config: common::ConfigFile<Config> = common::config(&config_dir, "Bookmarks");
     let common_config = common::common_config(&config_dir, "Bookmarks");
-
-    let browser_id = common::default_browser_id().unwrap_or_else(|e| {
-        eprintln!("Failed while getting default browser in init for bookmarks. Closing...:\n  {e}");
-        process::exit(1);
-    });
-    let browser = match browser_id.as_str() {
-        "firefox" => Box::new(common::Firefox::new(common_config.browser_profile_name())),
-        _ => {
-            eprintln!("(Bookmarks) Unsupported default browser! Closing...");
-            process::exit(1)
-        }
-    };
-    let bookmarks = browser.bookmarks().unwrap_or_else(|e| {
-        eprintln!("(Bookmarks) Failed while getting bookmarks. Closing...\n  {e}");
-        process::exit(1);
-    });
+    let loaded = common::Fallible::new("Bookmarks", &config_dir, load(&config, &common_config));
 
     InitData {
         config,
         common_config,
-        browser,
-        bookmarks,
+        loaded,
     }
 }
 
@@ -108,110 +88,119 @@ fn info() -> PluginInfo {
 }
 
 #[get_matches]
-fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
+fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    // Both files are always checked for changes, hence the non-short-circuiting "|":
+    if data.config.reload() | data.common_config.reload() {
+        let InitData {
+            config,
+            common_config,
+            loaded,
+        } = data;
+        loaded.reload(|| load(config, common_config));
+    }
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
+    }
+    matches
+}
+
+fn matches(input: RString, data: &mut InitData) -> RVec<Match> {
     let InitData {
         config,
-        common_config: _,
-        browser: _,
-        bookmarks,
+        common_config,
+        loaded,
     } = data;
 
+    // Keywords work without the prefix, but only input which is meant for this plugin retries
+    // loading the bookmarks (and shows the diagnostic), so other input doesn't hit the disk:
+    let loaded = if input.starts_with(config.prefix()) {
+        match loaded.get_or_retry(|| load(config, common_config)) {
+            Ok(loaded) => loaded,
+            Err(diagnostic) => return RVec::from(vec![*diagnostic]),
+        }
+    } else {
+        match loaded.get_mut() {
+            Some(loaded) => loaded,
+            None => return RVec::new(),
+        }
+    };
+
+    // A trailing modifier like "!p" isn't matched, it only changes how the bookmark opens:
+    let (input, open_mode) = common::open_mode(&input, common_config, config.browser());
+    loaded.open_mode = open_mode;
+
     // Early return if a keyword matches:
-    if let Some(bookmark) = bookmarks
+    if let Some(i) = loaded
+        .bookmarks
         .iter()
-        .find(|bookmark| !bookmark.keyword.is_empty() && &input == &bookmark.keyword)
+        .position(|bookmark| !bookmark.keyword.is_empty() && input == bookmark.keyword)
     {
+        let bookmark = &loaded.bookmarks[i];
         return RVec::from(vec![Match {
             title: RString::from(bookmark.title.as_str()),
             description: RSome(RString::from(bookmark.url.as_str())),
             use_pango: false,
             icon: RSome(RString::from("user-bookmarks-symbolic")),
-            id: RNone,
+            id: RSome(i as u64),
         }]);
     }
 
-    // Early return for the wrong prefix:
-    if !input.starts_with(config.prefix()) {
-        return RVec::new();
+    common::source::matches(&*loaded, &**config, input)
+}
+
+// The match ids hold the index of the bookmark, so the title and the URL can have
+// highlighting markup in them:
+impl common::ItemSource for Loaded {
+    type Item = Bookmark;
+
+    fn items(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    fn title<'a>(&'a self, bookmark: &'a Bookmark) -> Cow<'a, str> {
+        Cow::Borrowed(&bookmark.title)
     }
 
-    let stripped_input = input.strip_prefix(config.prefix()).unwrap().trim();
-
-    // Handling blank input:
-    if stripped_input.is_empty() {
-        match config.bib() {
-            common::Bib::All => {
-                return RVec::from_iter(bookmarks.into_iter().take(config.max_entries()).map(
-                    |bookmark| Match {
-                        title: RString::from(bookmark.title.as_str()),
-                        description: RSome(RString::from(bookmark.url.as_str())),
-                        use_pango: false,
-                        icon: RSome(RString::from("user-bookmarks-symbolic")),
-                        id: RNone,
-                    },
-                ))
-            }
-            common::Bib::None => return RVec::new(),
-            common::Bib::Currated(v) => {
-                return RVec::from_iter(
-                    bookmarks
-                        .into_iter()
-                        .filter(|bookmark| v.contains(&bookmark.title))
-                        .take(config.max_entries())
-                        .map(|bookmark| Match {
-                            title: RString::from(bookmark.title.as_str()),
-                            description: RSome(RString::from(bookmark.url.as_str())),
-                            use_pango: false,
-                            icon: RSome(RString::from("user-bookmarks-symbolic")),
-                            id: RNone,
-                        }),
-                )
-            }
-        }
+    fn description<'a>(&'a self, bookmark: &'a Bookmark) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(&bookmark.url))
     }
 
-    // Fuzzy matching
-    let matcher = SkimMatcherV2::default();
-    // Shadowing "bookmarks"; performing fuzzy matching:
-    let mut bookmarks: Vec<(i64, &Bookmark)> = bookmarks
-        .into_iter()
-        .filter_map(|bookmark| {
-            let score = matcher.fuzzy_match(&bookmark.title, stripped_input)?;
-            Some((score, bookmark))
-        })
-        .collect();
-    // Sorting bookmarks by score in descending order.
-    bookmarks.sort_by(|a, b| b.0.cmp(&a.0));
-
-    // SUCCESS
-    RVec::from_iter(
-        bookmarks
-            .iter()
-            .take(config.max_entries())
-            .map(|(_, bookmark)| Match {
-                title: RString::from(bookmark.title.as_str()),
-                description: RSome(RString::from(bookmark.url.as_str())),
-                use_pango: false,
-                icon: RSome(RString::from("user-bookmarks-symbolic")),
-                id: RNone,
-            }),
-    )
+    fn icon<'a>(&'a self, _bookmark: &'a Bookmark) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed("user-bookmarks-symbolic"))
+    }
+
+    fn activate(&self, bookmark: &Bookmark, common_config: &common::CommonConfig) -> HandleResult {
+        self.browser
+            .open(slice::from_ref(&bookmark.url), &self.open_mode, false, common_config)
+            .unwrap_or_else(|e| eprintln!("(Bookmarks) Failed while opening URL! Closing...\n  {e}"));
+
+        HandleResult::Close
+    }
 }
 
 #[handler]
 fn handler(selection: Match, data: &InitData) -> HandleResult {
     let InitData {
-        config: _,
+        config,
         common_config,
-        browser,
-        bookmarks: _,
+        loaded,
     } = data;
 
-    // Description MUST be Some, this is just how I have implemented it, that's why it is safe to .unwrap() here.
-    let url = &selection.description.unwrap();
-    browser
-        .new_window(url, common_config.prefix_args())
-        .unwrap_or_else(|e| eprintln!("(Webpages) Failed while opening URL! Closing...\n  {e}"));
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
+    }
 
-    HandleResult::Close
+    if let Some(result) = loaded.handle(&selection, common_config) {
+        return result;
+    }
+    // It is safe to unwrap here, because only the diagnostic match can be
+    // selected while the bookmarks are failing to load.
+    common::source::handle(loaded.get().unwrap(), &selection, common_config)
 }
@@ -0,0 +1,432 @@
+use freedesktop_desktop_entry::DesktopEntry;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, error::Error, fmt::Debug, fs};
+
+use crate::{launch, CommonConfig};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserKind {
+    Firefox,
+    Chromium,
+    Chrome,
+    Brave,
+}
+
+impl BrowserKind {
+    // Figures out the kind from a desktop file ID, e.g. "firefox", "firefox-esr" or "org.mozilla.firefox":
+    fn from_appid(appid: &str) -> Option<Self> {
+        let appid = appid.to_lowercase();
+        if appid.contains("firefox") {
+            Some(BrowserKind::Firefox)
+        } else if appid.contains("brave") {
+            Some(BrowserKind::Brave)
+        } else if appid.contains("chrome") {
+            Some(BrowserKind::Chrome)
+        } else if appid.contains("chromium") {
+            Some(BrowserKind::Chromium)
+        } else {
+            None
+        }
+    }
+}
+
+/// How a page is opened. `Profile` opens it in a new window of the named profile instead of
+/// the one from `profile`.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq, Default)]
+pub enum OpenMode {
+    Tab,
+    #[default]
+    Window,
+    Private,
+    Profile(String),
+}
+
+/// The browser settings from `Common.ron`. Every browser plugin can override any of these in
+/// its own config file, e.g. Bookmarks can read the "work" profile while Websearch opens
+/// searches in the "personal" one.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Default)]
+pub struct BrowserConfig {
+    /// The kind of browser. If it isn't set, it is inferred from the default browser.
+    pub(crate) kind: Option<BrowserKind>,
+    /// The binary which is used for opening pages.
+    #[serde(default, deserialize_with = "crate::expanded")]
+    pub(crate) binary: Option<String>,
+    /// The profile whose data (bookmarks, search engines) is read. It is also passed to
+    /// the browser when opening pages.
+    pub(crate) profile: Option<String>,
+    /// Extra arguments which are passed to the browser when opening pages.
+    #[serde(default, deserialize_with = "crate::expanded")]
+    pub(crate) args: Option<Vec<String>>,
+    /// How pages are opened, unless the query ends with one of the modifiers.
+    pub(crate) open_mode: Option<OpenMode>,
+    /// Words which open the page differently when the query ends with them, e.g. "!p" for a
+    /// private window.
+    pub(crate) modifiers: Option<BTreeMap<String, OpenMode>>,
+}
+
+impl BrowserConfig {
+    /// Merges field by field, preferring the fields of `self`.
+    pub fn or(&self, fallback: &BrowserConfig) -> BrowserConfig {
+        BrowserConfig {
+            kind: self.kind.or(fallback.kind),
+            binary: self.binary.clone().or_else(|| fallback.binary.clone()),
+            profile: self.profile.clone().or_else(|| fallback.profile.clone()),
+            args: self.args.clone().or_else(|| fallback.args.clone()),
+            open_mode: self
+                .open_mode
+                .clone()
+                .or_else(|| fallback.open_mode.clone()),
+            modifiers: self
+                .modifiers
+                .clone()
+                .or_else(|| fallback.modifiers.clone()),
+        }
+    }
+
+    pub fn open_mode(&self) -> OpenMode {
+        self.open_mode.clone().unwrap_or_default()
+    }
+    pub fn modifiers(&self) -> BTreeMap<String, OpenMode> {
+        self.modifiers.clone().unwrap_or_else(|| {
+            // Nothing short like "!w", because that is a bang in Websearch:
+            BTreeMap::from([
+                ("!p".to_string(), OpenMode::Private),
+                ("!tab".to_string(), OpenMode::Tab),
+                ("!win".to_string(), OpenMode::Window),
+            ])
+        })
+    }
+}
+
+/// Strips a trailing modifier (like "!p") from the input and returns the rest of the input
+/// together with the mode which the page should be opened in: the modifier's one, or else the
+/// configured one. It doesn't need the browser, so plugins can use it before loading that.
+pub fn open_mode<'a>(
+    input: &'a str,
+    common_config: &CommonConfig,
+    overrides: Option<&BrowserConfig>,
+) -> (&'a str, OpenMode) {
+    let config = resolve(common_config, overrides);
+    let input = input.trim_end();
+    // A modifier on its own isn't stripped, because then there wouldn't be anything to open:
+    if let Some((rest, word)) = input.rsplit_once(char::is_whitespace) {
+        if let Some(mode) = config.modifiers().remove(&word.to_lowercase()) {
+            return (rest.trim_end(), mode);
+        }
+    }
+    (input, config.open_mode())
+}
+
+fn resolve(common_config: &CommonConfig, overrides: Option<&BrowserConfig>) -> BrowserConfig {
+    match overrides {
+        Some(overrides) => overrides.or(common_config.browser()),
+        None => common_config.browser().clone(),
+    }
+}
+
+// The Send + Sync are necessary for creating structs:
+pub trait Browser: Send + Sync + Debug {
+    /// Opens all of the URLs with a single invocation of the browser. In a new window (or in
+    /// another profile) they become tabs of that one window, unless `separate_windows` is set.
+    fn open(
+        &self,
+        urls: &[String],
+        mode: &OpenMode,
+        separate_windows: bool,
+        common_config: &CommonConfig,
+    ) -> Result<(), Box<dyn Error>>;
+    fn name(&self) -> String;
+    fn icon(&self) -> String;
+}
+
+// NOTE 1
+/// Every browser that the plugins know how to talk to. The plugins match on this and box
+/// the browser as whichever of their own traits (like `Bookmarks`) they need.
+#[derive(Debug)]
+pub enum KnownBrowser {
+    Firefox(Firefox),
+    Chromium(Chromium),
+}
+
+/// Resolves the browser from the common config, the plugin's overrides and the default browser.
+pub fn browser(
+    common_config: &CommonConfig,
+    overrides: Option<&BrowserConfig>,
+) -> Result<KnownBrowser, Box<dyn Error>> {
+    let config = resolve(common_config, overrides);
+
+    // The default browser's Desktop Entry is only needed for the name and icon when the
+    // kind is pinned, so failing to find it is not an error then:
+    let entry = crate::default_browser();
+    let kind = match (config.kind, &entry) {
+        (Some(kind), _) => kind,
+        (None, Ok(entry)) => BrowserKind::from_appid(&entry.appid)
+            .ok_or_else(|| format!("Unsupported default browser \"{}\"!", entry.appid))?,
+        (None, Err(e)) => {
+            return Err(format!("Failed while getting the default browser:\n    {e}").into())
+        }
+    };
+    let entry = entry
+        .ok()
+        .filter(|entry| BrowserKind::from_appid(&entry.appid) == Some(kind));
+
+    match kind {
+        BrowserKind::Firefox => Ok(KnownBrowser::Firefox(Firefox {
+            profile_name: config
+                .profile
+                .clone()
+                .unwrap_or_else(|| common_config.browser_profile_name().to_string()),
+            launch_profile: config.profile,
+            binary: config.binary.unwrap_or_else(|| "firefox".to_string()),
+            args: config.args.unwrap_or_default(),
+            entry,
+        })),
+        // Chromium and the browsers built on it only differ in their directories and names:
+        BrowserKind::Chromium | BrowserKind::Chrome | BrowserKind::Brave => {
+            let (config_dir, binary, name, app_id) = match kind {
+                BrowserKind::Chrome => (
+                    "google-chrome",
+                    "google-chrome-stable",
+                    "Google Chrome",
+                    "google-chrome",
+                ),
+                BrowserKind::Brave => (
+                    "BraveSoftware/Brave-Browser",
+                    "brave-browser",
+                    "Brave",
+                    "brave-browser",
+                ),
+                _ => ("chromium", "chromium", "Chromium", "chromium"),
+            };
+            Ok(KnownBrowser::Chromium(Chromium {
+                config_dir,
+                profile_dir_name: config
+                    .profile
+                    .clone()
+                    .unwrap_or_else(|| "Default".to_string()),
+                launch_profile: config.profile,
+                binary: config.binary.unwrap_or_else(|| binary.to_string()),
+                args: config.args.unwrap_or_default(),
+                name,
+                app_id,
+                entry,
+            }))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Firefox {
+    // Used for finding the profile directory:
+    profile_name: String,
+    // Only a profile that was set explicitly is passed to Firefox with "-P", because the
+    // fallback "default" doesn't have to be the name of an actual profile:
+    launch_profile: Option<String>,
+    binary: String,
+    args: Vec<String>,
+    entry: Option<DesktopEntry<'static>>,
+}
+
+impl Firefox {
+    pub fn profile_dir(&self) -> Result<String, Box<dyn Error>> {
+        let home_dir = env::var("HOME").map_err(|e| {
+            format!("HOME env variable not set! Bookmarks cannot be obtained:\n    {e}")
+        })?;
+
+        let firefox_path = format!("{home_dir}/.mozilla/firefox");
+
+        // The profile directory may have a name like "<random_characters>.<name>"
+        // or just "<name>". Because it cannot be inferred from the profile name alone,
+        // a .find() method is required to find the target dir:
+        let profile_dirname = fs::read_dir(&firefox_path)
+            .map_err(|e| format!("Failed while reading firefox directory:\n    {e}"))?
+            // At this point we have an Iterator over Result<DirEntry, Error> and
+            // we want the DirEntry which corresponds to the profile directory:
+            .find_map(|entry| match entry {
+                Ok(dir_entry) => {
+                    if dir_entry
+                        .file_name()
+                        .to_string_lossy()
+                        .contains(&self.profile_name)
+                    {
+                        Some(dir_entry)
+                    } else {
+                        None
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed while unwrapping entry of {firefox_path}:\n    {e}");
+                    None
+                }
+            })
+            // At this point we either have the DirEntry we need or we don't. We have to
+            // return from the function with an Err if we didn't find the directory:
+            .ok_or_else(|| {
+                format!(
+                    "Cannot find the profile directory, please make sure that \
+                    the profile with name {} exists!",
+                    self.profile_name
+                )
+            })?
+            // In this part of the code we are converting DirEntry to String:
+            .file_name()
+            .to_string_lossy()
+            .into_owned();
+
+        Ok(format!("{firefox_path}/{profile_dirname}"))
+    }
+}
+
+impl Firefox {
+    // Firefox with the profile and the extra args, but without any URLs yet:
+    fn launcher(&self, profile: Option<&str>, common_config: &CommonConfig) -> launch::Launcher {
+        let mut launcher = common_config.launcher(&self.binary).args(&self.args);
+        if let Some(profile) = profile.or(self.launch_profile.as_deref()) {
+            launcher = launcher.args(&["-P", profile]);
+        }
+        launcher.startup(match &self.entry {
+            Some(entry) => launch::Startup::from_desktop_entry(entry),
+            None => launch::Startup::new("firefox"),
+        })
+    }
+}
+
+impl Browser for Firefox {
+    fn open(
+        &self,
+        urls: &[String],
+        mode: &OpenMode,
+        separate_windows: bool,
+        common_config: &CommonConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let profile = match mode {
+            OpenMode::Profile(profile) => Some(profile.as_str()),
+            _ => None,
+        };
+        // Tabs are opened in the window which was opened last, which is the new one:
+        let mut launcher = self.launcher(profile, common_config);
+        for (i, url) in urls.iter().enumerate() {
+            let flag = match mode {
+                OpenMode::Tab => "--new-tab",
+                OpenMode::Private => "--private-window",
+                OpenMode::Window | OpenMode::Profile(_) if i == 0 || separate_windows => {
+                    "--new-window"
+                }
+                OpenMode::Window | OpenMode::Profile(_) => "--new-tab",
+            };
+            launcher = launcher.args(&[flag, url.as_str()]);
+        }
+        launcher.spawn()
+    }
+
+    fn name(&self) -> String {
+        self.entry
+            .as_ref()
+            .and_then(|entry| entry.name::<&str>(&[]))
+            .map(|name| name.into_owned())
+            .unwrap_or_else(|| "Firefox".to_string())
+    }
+
+    fn icon(&self) -> String {
+        self.entry
+            .as_ref()
+            .and_then(|entry| entry.icon())
+            .unwrap_or("firefox")
+            .to_string()
+    }
+}
+
+#[derive(Debug)]
+pub struct Chromium {
+    // The browser's directory in $XDG_CONFIG_HOME, e.g. "chromium":
+    config_dir: &'static str,
+    // The profile is a directory there, like "Default" or "Profile 1":
+    profile_dir_name: String,
+    // Like with Firefox, only a profile that was set explicitly is passed to the browser:
+    launch_profile: Option<String>,
+    binary: String,
+    args: Vec<String>,
+    // The name and the desktop file ID (which is the icon as well) for when the browser isn't
+    // the default one:
+    name: &'static str,
+    app_id: &'static str,
+    entry: Option<DesktopEntry<'static>>,
+}
+
+impl Chromium {
+    pub fn profile_dir(&self) -> Result<String, Box<dyn Error>> {
+        let profile_dir = crate::xdg::config_home()?
+            .join(self.config_dir)
+            .join(&self.profile_dir_name);
+        if !profile_dir.is_dir() {
+            return Err(format!(
+                "Cannot find the profile directory {profile_dir:?}, please make sure that \
+                the profile \"{}\" exists!",
+                self.profile_dir_name
+            )
+            .into());
+        }
+        Ok(profile_dir.to_string_lossy().into_owned())
+    }
+
+    // The browser with the profile and the extra args, but without any URLs yet:
+    fn launcher(&self, profile: Option<&str>, common_config: &CommonConfig) -> launch::Launcher {
+        let mut launcher = common_config.launcher(&self.binary).args(&self.args);
+        if let Some(profile) = profile.or(self.launch_profile.as_deref()) {
+            launcher = launcher.arg(format!("--profile-directory={profile}"));
+        }
+        launcher.startup(match &self.entry {
+            Some(entry) => launch::Startup::from_desktop_entry(entry),
+            None => launch::Startup::new(self.app_id),
+        })
+    }
+}
+
+impl Browser for Chromium {
+    fn open(
+        &self,
+        urls: &[String],
+        mode: &OpenMode,
+        separate_windows: bool,
+        common_config: &CommonConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let (profile, flag) = match mode {
+            // Without a flag, the URLs become tabs of the window which was used last:
+            OpenMode::Tab => (None, None),
+            OpenMode::Window => (None, Some("--new-window")),
+            OpenMode::Private => (None, Some("--incognito")),
+            OpenMode::Profile(profile) => (Some(profile.as_str()), Some("--new-window")),
+        };
+        // Every URL of an invocation is opened in the same window, so separate windows need
+        // an invocation each:
+        let invocations: Vec<&[String]> = match separate_windows && flag.is_some() {
+            true => urls.chunks(1).collect(),
+            false => vec![urls],
+        };
+        for urls in invocations {
+            let mut launcher = self.launcher(profile, common_config);
+            if let Some(flag) = flag {
+                launcher = launcher.arg(flag);
+            }
+            launcher.args(urls).spawn()?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        self.entry
+            .as_ref()
+            .and_then(|entry| entry.name::<&str>(&[]))
+            .map(|name| name.into_owned())
+            .unwrap_or_else(|| self.name.to_string())
+    }
+
+    fn icon(&self) -> String {
+        self.entry
+            .as_ref()
+            .and_then(|entry| entry.icon())
+            .unwrap_or(self.app_id)
+            .to_string()
+    }
+}
@@ -0,0 +1,651 @@
+use anyrun_plugin::{HandleResult, Match};
+use ron::error::SpannedError;
+use ron::ser::PrettyConfig;
+use schemars::{schema::Schema, JsonSchema};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt, fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{diagnostic, xdg, CommonConfig};
+
+// A problem found in a config file. Some problems (like an unknown field nested in
+// another field) can't be traced back to an exact place in the file:
+struct Issue {
+    // Only set for problems in the system-wide file, because the user's own file is implied:
+    file: Option<PathBuf>,
+    position: Option<(usize, usize)>,
+    message: String,
+    // Unknown fields are only warnings, because the rest of the config is still fine:
+    warning: bool,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        match self.position {
+            Some((line, col)) => write!(f, "line {line}, column {col}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// A top level field of the config, e.g. `prefix: Some("!")`. The range is in bytes and
+// it includes the trailing comma if the field has one:
+struct Field {
+    name: String,
+    start: usize,
+    end: usize,
+}
+
+// Where the fields of the config struct are. Everything before `open` (like the
+// `#![enable(implicit_some)]` attributes and the struct name) is kept as it is:
+struct Layout {
+    open: usize,
+    close: usize,
+    fields: Vec<Field>,
+}
+
+impl Layout {
+    // Figures out the layout without actually parsing the values, so it even works when
+    // the values have mistakes in them. Returns None if the brackets don't add up:
+    fn new(src: &str) -> Option<Layout> {
+        let bytes = src.as_bytes();
+        let mut i = skip_trivia(bytes, 0);
+        // Attributes like #![enable(implicit_some)]:
+        while bytes[i..].starts_with(b"#!") {
+            i += bytes[i..].iter().position(|&b| b == b']')? + 1;
+            i = skip_trivia(bytes, i);
+        }
+        // The struct name is optional:
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            i += 1;
+        }
+        i = skip_trivia(bytes, i);
+        if bytes.get(i) != Some(&b'(') {
+            return None;
+        }
+        let open = i + 1;
+
+        let mut fields = Vec::new();
+        i = open;
+        loop {
+            i = skip_trivia(bytes, i);
+            if bytes.get(i) == Some(&b')') {
+                return Some(Layout {
+                    open,
+                    close: i,
+                    fields,
+                });
+            }
+            let start = i;
+            let name_end = start
+                + bytes[start..]
+                    .iter()
+                    .position(|&b| !(b.is_ascii_alphanumeric() || b == b'_' || b == b'#'))?;
+            let name = src[start..name_end].trim_start_matches("r#").to_string();
+
+            let (end, last) = value_end(bytes, name_end)?;
+            fields.push(Field { name, start, end });
+            if last {
+                return Some(Layout {
+                    open,
+                    close: end,
+                    fields,
+                });
+            }
+            i = end;
+        }
+    }
+
+    // The config with every field except the given ones blanked out. Newlines are kept
+    // and every other character becomes a single space, so the positions ron reports are
+    // the positions in the original file:
+    fn only(&self, src: &str, fields: &[&Field]) -> String {
+        src.char_indices()
+            .map(|(i, c)| {
+                let kept = i < self.open
+                    || i >= self.close
+                    || fields
+                        .iter()
+                        .any(|field| (field.start..field.end).contains(&i));
+                if kept || c == '\n' {
+                    c
+                } else {
+                    ' '
+                }
+            })
+            .collect()
+    }
+
+    fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+// Skips whitespace and comments (block comments can be nested in RON):
+fn skip_trivia(bytes: &[u8], mut i: usize) -> usize {
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes[i..].starts_with(b"//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if bytes[i..].starts_with(b"/*") {
+            let mut depth = 0;
+            while i < bytes.len() {
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else {
+            return i;
+        }
+    }
+}
+
+// Finds the end of a field's value. Returns the index right after the separating comma,
+// or the index of the closing bracket of the struct (then the bool is true):
+fn value_end(bytes: &[u8], mut i: usize) -> Option<(usize, bool)> {
+    let mut depth = 0usize;
+    while i < bytes.len() {
+        if let Some(end) = literal_end(bytes, i) {
+            i = end?;
+            continue;
+        }
+        match bytes[i] {
+            b'/' if bytes[i..].starts_with(b"//") || bytes[i..].starts_with(b"/*") => {
+                i = skip_trivia(bytes, i);
+                continue;
+            }
+            b'(' | b'[' | b'{' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' if depth == 0 => return Some((i, true)),
+            b')' | b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                i += 1;
+            }
+            b',' if depth == 0 => return Some((i + 1, false)),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// If a string, a raw string (e.g. r#"C:\path"#) or a char starts at `i`, returns the index
+// right after it. The inner None means that the literal is never closed:
+fn literal_end(bytes: &[u8], i: usize) -> Option<Option<usize>> {
+    match bytes[i] {
+        b'"' => Some(string_end(bytes, i + 1, 0)),
+        b'r' => {
+            let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+            (bytes.get(i + 1 + hashes) == Some(&b'"'))
+                .then(|| string_end(bytes, i + 2 + hashes, hashes))
+        }
+        b'\'' => {
+            let start = if bytes.get(i + 1) == Some(&b'\\') {
+                i + 2
+            } else {
+                i + 1
+            };
+            Some(
+                bytes
+                    .get(start + 1..)
+                    .and_then(|rest| rest.iter().position(|&b| b == b'\''))
+                    .map(|len| start + len + 2),
+            )
+        }
+        _ => None,
+    }
+}
+
+// Returns the index right after the closing quote of a string which starts at `i`:
+fn string_end(bytes: &[u8], mut i: usize, hashes: usize) -> Option<usize> {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if hashes == 0 => i += 2,
+            b'"' if bytes[i + 1..]
+                .iter()
+                .take(hashes)
+                .filter(|&&b| b == b'#')
+                .count()
+                == hashes =>
+            {
+                return Some(i + 1 + hashes)
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// Deserializes the config and collects the paths of the fields that were ignored
+// because the config struct doesn't have them:
+fn deserialize<T: DeserializeOwned>(src: &str) -> Result<(T, Vec<String>), SpannedError> {
+    let mut deserializer = ron::Deserializer::from_str(src)?;
+    let mut unknown = Vec::new();
+    let value = serde_ignored::deserialize(&mut deserializer, |path| {
+        // serde_ignored marks Option and newtype layers with "?", which are just noise here:
+        let path = path
+            .to_string()
+            .split('.')
+            .filter(|segment| *segment != "?")
+            .collect::<Vec<_>>()
+            .join(".");
+        unknown.push(path)
+    })
+    .map_err(|e| deserializer.span_error(e))?;
+    deserializer.end().map_err(|e| deserializer.span_error(e))?;
+    Ok((value, unknown))
+}
+
+fn spanned_issue(e: &SpannedError, message: String) -> Issue {
+    Issue {
+        file: None,
+        position: Some((e.position.line, e.position.col)),
+        message,
+        warning: false,
+    }
+}
+
+fn unknown_issues(unknown: Vec<String>, layout: Option<&Layout>, src: &str) -> Vec<Issue> {
+    unknown
+        .into_iter()
+        .map(|path| {
+            let top_level = path.split('.').next().unwrap_or_default();
+            let position = layout
+                .and_then(|layout| layout.field(top_level))
+                .map(|field| position(src, field.start));
+            Issue {
+                file: None,
+                position,
+                message: format!("Unknown field \"{path}\" is ignored"),
+                warning: true,
+            }
+        })
+        .collect()
+}
+
+// The 1-based line and column of a byte index, counted the same way as ron counts them:
+fn position(src: &str, index: usize) -> (usize, usize) {
+    let before = &src[..index];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, col)
+}
+
+// Parses the config field by field. A field with a mistake in it falls back to its
+// default, instead of the whole file falling back to the default config:
+fn parse<T: DeserializeOwned + Default>(src: &str) -> (T, Vec<Issue>) {
+    let layout = Layout::new(src);
+    let e = match deserialize::<T>(src) {
+        Ok((value, unknown)) => return (value, unknown_issues(unknown, layout.as_ref(), src)),
+        Err(e) => e,
+    };
+
+    let Some(layout) = layout else {
+        // The fields can't even be told apart, so there is nothing to salvage:
+        let message = format!("{}. Every field falls back to default", e.code);
+        return (T::default(), vec![spanned_issue(&e, message)]);
+    };
+
+    let mut issues = Vec::new();
+    let mut valid = Vec::new();
+    for field in &layout.fields {
+        match deserialize::<T>(&layout.only(src, &[field])) {
+            Ok(_) => valid.push(field),
+            Err(e) => {
+                let message = format!("{}. \"{}\" falls back to default", e.code, field.name);
+                issues.push(spanned_issue(&e, message))
+            }
+        }
+    }
+
+    // The valid fields can still clash with each other, e.g. when a field is set twice:
+    match deserialize::<T>(&layout.only(src, &valid)) {
+        Ok((value, unknown)) => {
+            issues.extend(unknown_issues(unknown, Some(&layout), src));
+            (value, issues)
+        }
+        Err(e) => {
+            let message = format!("{}. Every field falls back to default", e.code);
+            issues.push(spanned_issue(&e, message));
+            (T::default(), issues)
+        }
+    }
+}
+
+// The fields of a config which are valid on their own, as (name, text) pairs:
+fn valid_fields<T: DeserializeOwned>(src: &str) -> Vec<(String, String)> {
+    let Some(layout) = Layout::new(src) else {
+        return Vec::new();
+    };
+    layout
+        .fields
+        .iter()
+        .filter(|field| deserialize::<T>(&layout.only(src, &[field])).is_ok())
+        .map(|field| {
+            let text = src[field.start..field.end].trim_end_matches(',');
+            (field.name.clone(), text.to_string())
+        })
+        .collect()
+}
+
+// Puts the fields the user doesn't set (or sets to something invalid) into the user's config.
+// They go right before the closing bracket, so the positions of the user's own fields don't
+// change. Also returns where the inserted fields start:
+fn insert_fields<T: DeserializeOwned>(
+    src: &str,
+    fields: &[(String, String)],
+) -> (String, Option<(usize, usize)>) {
+    let Some(layout) = Layout::new(src) else {
+        return (src.to_string(), None);
+    };
+    // An invalid field is dropped while parsing, so the lower layers can fill it in:
+    let valid = valid_fields::<T>(src);
+    // A field can be in more than one of the lower layers, and the first one wins:
+    let mut missing: Vec<&(String, String)> = Vec::new();
+    for field in fields {
+        if valid.iter().all(|(name, _)| *name != field.0)
+            && missing.iter().all(|(name, _)| *name != field.0)
+        {
+            missing.push(field);
+        }
+    }
+    if missing.is_empty() {
+        return (src.to_string(), None);
+    }
+
+    let mut merged = src[..layout.close].to_string();
+    // The last field only ends at the closing bracket if it doesn't have a trailing comma:
+    if layout
+        .fields
+        .last()
+        .is_some_and(|field| field.end == layout.close)
+    {
+        merged.push(',');
+    }
+    let missing: Vec<&str> = missing.iter().map(|(_, text)| text.as_str()).collect();
+    merged.push_str(&missing.join(","));
+    merged.push_str(&src[layout.close..]);
+    (merged, Some(position(src, layout.close)))
+}
+
+// The system-wide config file (e.g. /etc/xdg/anyrun/Common.ron), which can be used to ship
+// defaults for every user. Like with any XDG config dir, the first one with the file wins:
+fn system_file(file_name: &str) -> Option<PathBuf> {
+    xdg::config_dirs()
+        .into_iter()
+        .map(|dir| dir.join("anyrun").join(file_name))
+        .find(|path| path.is_file())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// The modification times of the user's and the system-wide file, so a change to either
+// of them (including one of them appearing or disappearing) is noticed:
+type Stamp = (Option<SystemTime>, Option<SystemTime>);
+
+fn stamp(path: &Path, file_name: &str) -> Stamp {
+    (
+        modified(path),
+        system_file(file_name).and_then(|system| modified(&system)),
+    )
+}
+
+// Not having the file at all is perfectly fine, so that isn't an issue:
+fn read_source(path: &Path) -> Result<Option<String>, Issue> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Issue {
+            file: None,
+            position: None,
+            message: format!("{e}. Falling back to default"),
+            warning: false,
+        }),
+    }
+}
+
+fn in_file(file: &Path, issues: Vec<Issue>) -> impl Iterator<Item = Issue> + '_ {
+    issues.into_iter().map(move |issue| Issue {
+        file: Some(file.to_path_buf()),
+        ..issue
+    })
+}
+
+// Reads the user's config file on top of the system-wide one and the defaults. Every field
+// that the user doesn't set is taken from the system-wide file, and every field that neither
+// of them sets is taken from the Default impl. The modification times are read before the
+// contents, so a write that happens in between is picked up by the next reload:
+fn read<T>(path: &Path, file_name: &str) -> (Stamp, T, Vec<Issue>)
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let stamp = stamp(path, file_name);
+    let mut issues = Vec::new();
+
+    let system = system_file(file_name).and_then(|system| match read_source(&system) {
+        Ok(src) => src.map(|src| (system, src)),
+        Err(issue) => {
+            issues.extend(in_file(&system, vec![issue]));
+            None
+        }
+    });
+    let user = read_source(path).unwrap_or_else(|issue| {
+        issues.push(issue);
+        None
+    });
+
+    let mut lower = Vec::new();
+    let (src, file) = match (user, system) {
+        (Some(src), Some((system, system_src))) => {
+            issues.extend(in_file(&system, parse::<T>(&system_src).1));
+            lower = valid_fields::<T>(&system_src);
+            (src, None)
+        }
+        (Some(src), None) => (src, None),
+        (None, Some((system, system_src))) => (system_src, Some(system)),
+        (None, None) => (String::from("()"), None),
+    };
+    // The defaults are serialized and inserted like any other layer, so the config always
+    // ends up with the values from the Default impl and not whatever the accessors fall back to:
+    let defaults = ron::to_string(&T::default()).unwrap_or_else(|_| String::from("()"));
+    lower.extend(valid_fields::<T>(&defaults));
+
+    let (merged, inserted) = insert_fields::<T>(&src, &lower);
+    let (value, merged_issues) = parse::<T>(&merged);
+    // The inserted fields were already checked, so only the problems before them count:
+    let merged_issues: Vec<Issue> = merged_issues
+        .into_iter()
+        .filter(|issue| match (issue.position, inserted) {
+            (Some(position), Some(inserted)) => position < inserted,
+            _ => true,
+        })
+        .collect();
+    match file {
+        Some(system) => issues.extend(in_file(&system, merged_issues)),
+        None => issues.extend(merged_issues),
+    }
+
+    (stamp, value, issues)
+}
+
+/// If this env variable holds a path, plugins built with the `harness` feature create that
+/// file as soon as they load a config file. Plugins only do that in their init, which holds
+/// their data until it is done, so once the file exists, every query waits for init.
+pub const INIT_MARKER_VAR: &str = "ANYRUN_PLUGINS_INIT_MARKER";
+
+/// A parsed config file. It derefs to the config itself, and it keeps whatever problems were
+/// found in the file so a plugin can show them as a diagnostic match.
+pub struct ConfigFile<T> {
+    plugin: String,
+    config_dir: String,
+    file_name: String,
+    value: T,
+    issues: Vec<String>,
+    stamp: Stamp,
+}
+
+impl<T: DeserializeOwned + Serialize + Default> ConfigFile<T> {
+    pub fn load(config_dir: &str, plugin: &str, file_name: &str) -> Self {
+        #[cfg(feature = "harness")]
+        if let Some(path) = std::env::var_os(INIT_MARKER_VAR) {
+            let _ = fs::write(path, "");
+        }
+
+        let config_file = Self::load_quietly(config_dir, plugin, file_name);
+        config_file.report();
+        config_file
+    }
+
+    /// Loads the file without printing its problems to stderr.
+    pub fn load_quietly(config_dir: &str, plugin: &str, file_name: &str) -> Self {
+        let path = Path::new(config_dir).join(file_name);
+        let (stamp, value, issues) = read::<T>(&path, file_name);
+        ConfigFile {
+            plugin: plugin.to_string(),
+            config_dir: config_dir.to_string(),
+            file_name: file_name.to_string(),
+            value,
+            issues: issues.iter().map(Issue::to_string).collect(),
+            stamp,
+        }
+    }
+
+    /// Reads the file again if it (or the system-wide file) changed since it was last read.
+    /// Returns true if the config changed. An edit with mistakes in it keeps the previous
+    /// config, so a half-written file doesn't reset everything to default.
+    pub fn reload(&mut self) -> bool {
+        let path = Path::new(&self.config_dir).join(&self.file_name);
+        if stamp(&path, &self.file_name) == self.stamp {
+            return false;
+        }
+
+        let (stamp, value, issues) = read::<T>(&path, &self.file_name);
+        self.stamp = stamp;
+        self.issues = issues.iter().map(Issue::to_string).collect();
+        let changed = if issues.iter().all(|issue| issue.warning) {
+            self.value = value;
+            true
+        } else {
+            self.issues
+                .push("The previous config is kept until the mistakes are fixed".to_string());
+            false
+        };
+        self.report();
+        changed
+    }
+
+    fn report(&self) {
+        for issue in &self.issues {
+            eprintln!(
+                "({}) Problem in {}:\n  {issue}",
+                self.plugin, self.file_name
+            );
+        }
+    }
+}
+
+impl<T> ConfigFile<T> {
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    fn title(&self) -> String {
+        format!("({}) Problems in {}", self.plugin, self.file_name)
+    }
+
+    /// The match listing the problems in the file, if there are any.
+    pub fn diagnostic(&self) -> Option<Match> {
+        if self.issues.is_empty() {
+            return None;
+        }
+        Some(diagnostic::diagnostic_match(
+            &self.title(),
+            &self.issues.join("; "),
+        ))
+    }
+
+    /// Handles the selection if it is this file's diagnostic match.
+    pub fn handle(&self, selection: &Match, common_config: &CommonConfig) -> Option<HandleResult> {
+        (diagnostic::is_diagnostic(selection) && selection.title.as_str() == self.title()).then(
+            || {
+                diagnostic::open_config(
+                    &self.config_dir,
+                    &self.file_name,
+                    &self.plugin,
+                    common_config,
+                )
+            },
+        )
+    }
+}
+
+impl<T> Deref for ConfigFile<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// A config file with the default value of every field, and the field's doc comment above it.
+pub fn template<T: Serialize + Default + JsonSchema>(struct_name: &str) -> String {
+    let defaults = ron::ser::to_string_pretty(&T::default(), PrettyConfig::new())
+        .unwrap_or_else(|_| String::from("()"));
+    let Some(layout) = Layout::new(&defaults) else {
+        return defaults;
+    };
+    let properties = schemars::schema_for!(T)
+        .schema
+        .object
+        .map(|object| object.properties)
+        .unwrap_or_default();
+
+    let mut template = format!("{struct_name}(\n");
+    for field in &layout.fields {
+        let description = match properties.get(&field.name) {
+            Some(Schema::Object(schema)) => schema
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.description.clone()),
+            _ => None,
+        };
+        for line in description.unwrap_or_default().lines() {
+            let comment = format!("    // {line}");
+            template.push_str(comment.trim_end());
+            template.push('\n');
+        }
+        let text = defaults[field.start..field.end].trim_end_matches(',');
+        template.push_str(&format!("    {text},\n"));
+    }
+    template.push(')');
+    template
+}
@@ -0,0 +1,49 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+
+/// A copy of an SQLite database which its browser keeps locked while it runs, like Chromium's
+/// Web Data. The journal is copied too (if there is one), so an unfinished write is rolled
+/// back instead of read. The copy lives in a new private temporary directory, which
+/// is removed again when it is dropped.
+pub struct DatabaseCopy {
+    dir: TempDir,
+    file_name: String,
+}
+
+impl DatabaseCopy {
+    pub fn new(dir: impl AsRef<Path>, file_name: &str) -> Result<Self, Box<dyn Error>> {
+        let copy = DatabaseCopy {
+            dir: tempfile::Builder::new()
+                .prefix("anyrun-plugins-")
+                .tempdir()
+                .map_err(|e| format!("Failed while creating a temporary directory:\n    {e}"))?,
+            file_name: file_name.to_string(),
+        };
+        for suffix in ["", "-journal", "-wal"] {
+            let source = dir.as_ref().join(format!("{file_name}{suffix}"));
+            match fs::copy(
+                &source,
+                copy.dir.path().join(format!("{file_name}{suffix}")),
+            ) {
+                Ok(_) => (),
+                // Only the database itself always exists:
+                Err(_) if !suffix.is_empty() => (),
+                Err(e) => {
+                    return Err(
+                        format!("Failed while copying {}:\n    {e}", source.display()).into(),
+                    )
+                }
+            }
+        }
+        Ok(copy)
+    }
+
+    /// The path of the copied database, to be opened instead of the original.
+    pub fn path(&self) -> PathBuf {
+        self.dir.path().join(&self.file_name)
+    }
+}
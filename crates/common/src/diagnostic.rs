@@ -0,0 +1,124 @@
+use abi_stable::std_types::{ROption::RSome, RString};
+use anyrun_plugin::{HandleResult, Match};
+use std::{error::Error, path::PathBuf};
+
+use crate::CommonConfig;
+
+// Diagnostic matches use an id that no plugin uses for its own matches:
+pub const DIAGNOSTIC_ID: u64 = u64::MAX;
+
+/// Creates a match which explains a problem with the plugin. Selecting it opens the config file.
+pub fn diagnostic_match(title: &str, problem: &str) -> Match {
+    // Anyrun shows the description on a single line, so the indented lines
+    // of nested errors are joined together:
+    let description = problem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Match {
+        title: RString::from(title),
+        description: RSome(RString::from(description)),
+        use_pango: false,
+        icon: RSome(RString::from("dialog-error")),
+        id: RSome(DIAGNOSTIC_ID),
+    }
+}
+
+pub fn is_diagnostic(selection: &Match) -> bool {
+    selection.id == RSome(DIAGNOSTIC_ID)
+}
+
+/// Opens a config file, or the config directory if the file doesn't exist yet.
+pub fn open_config(
+    config_dir: &str,
+    file_name: &str,
+    plugin: &str,
+    common_config: &CommonConfig,
+) -> HandleResult {
+    let config_file = PathBuf::from(format!("{config_dir}/{file_name}"));
+    let target = if config_file.exists() {
+        config_file
+    } else {
+        PathBuf::from(config_dir)
+    };
+    common_config
+        .launcher("xdg-open")
+        .arg(target)
+        .spawn()
+        .unwrap_or_else(|e| eprintln!("({plugin}) Failed while opening the config file:\n  {e}"));
+    HandleResult::Close
+}
+
+/// The part of a plugin's data that can fail to load. Instead of killing anyrun (plugins
+/// run inside its process), a plugin keeps the error, shows it as a single diagnostic match
+/// and tries to load the data again on the next query.
+pub struct Fallible<T> {
+    plugin: String,
+    config_dir: String,
+    state: Result<T, String>,
+}
+
+impl<T> Fallible<T> {
+    fn title(&self) -> String {
+        format!("({}) Failed to initialize", self.plugin)
+    }
+
+    pub fn new(plugin: &str, config_dir: &str, state: Result<T, Box<dyn Error>>) -> Self {
+        Fallible {
+            plugin: plugin.to_string(),
+            config_dir: config_dir.to_string(),
+            state: state.map_err(|e| {
+                eprintln!(
+                    "({plugin}) Failed while initializing. Retrying on the next query...\n  {e}"
+                );
+                e.to_string()
+            }),
+        }
+    }
+
+    /// Returns the data, retrying `init` first if it failed before. If it still fails,
+    /// the diagnostic match which should be shown instead of any results is returned
+    /// (boxed, because a Match is much bigger than the reference to the data).
+    pub fn get_or_retry(
+        &mut self,
+        init: impl FnOnce() -> Result<T, Box<dyn Error>>,
+    ) -> Result<&mut T, Box<Match>> {
+        if self.state.is_err() {
+            self.state = init().map_err(|e| e.to_string());
+        }
+        let title = self.title();
+        self.state
+            .as_mut()
+            .map_err(|e| Box::new(diagnostic_match(&title, e)))
+    }
+
+    /// Loads the data again, e.g. after the config changed. If that fails, the data which
+    /// was loaded before is kept.
+    pub fn reload(&mut self, init: impl FnOnce() -> Result<T, Box<dyn Error>>) {
+        match init() {
+            Ok(value) => self.state = Ok(value),
+            Err(e) if self.state.is_ok() => eprintln!(
+                "({}) Failed while reloading. Keeping the previous data...\n  {e}",
+                self.plugin
+            ),
+            Err(e) => self.state = Err(e.to_string()),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.state.as_ref().ok()
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.state.as_mut().ok()
+    }
+
+    /// Handles the selection if it is the diagnostic match.
+    pub fn handle(&self, selection: &Match, common_config: &CommonConfig) -> Option<HandleResult> {
+        let config_file = format!("{}.ron", self.plugin);
+        (is_diagnostic(selection) && selection.title.as_str() == self.title())
+            .then(|| open_config(&self.config_dir, &config_file, &self.plugin, common_config))
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, env};
+
+/// Expands a leading `~` and `$VAR`, `${VAR}` and `${VAR:-default}` anywhere in the string, the
+/// same way a shell would. A variable which isn't set expands to an empty string, and `$$` can
+/// be used for a literal `$`.
+pub fn expand(s: &str) -> String {
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+
+    // Only "~" and "~/..." are expanded, "~user" isn't supported:
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with('/') {
+            if let Ok(home) = env::var("HOME") {
+                expanded.push_str(&home);
+                rest = after;
+            }
+        }
+    }
+
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let Some(end) = after.find('}') else {
+                // An unclosed "${" is left as it is:
+                expanded.push_str("${");
+                rest = after;
+                continue;
+            };
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, default),
+                None => (&after[..end], ""),
+            };
+            match env::var(name) {
+                Ok(value) if !value.is_empty() => expanded.push_str(&value),
+                _ => expanded.push_str(default),
+            }
+            rest = &after[end + 1..];
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            // A "$" which isn't followed by a name is just a "$":
+            if len == 0 {
+                expanded.push('$');
+                continue;
+            }
+            expanded.push_str(&env::var(&rest[..len]).unwrap_or_default());
+            rest = &rest[len..];
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Config values whose strings are expanded with `expand` after they are read.
+pub trait Expand {
+    fn expand(self) -> Self;
+}
+
+impl Expand for String {
+    fn expand(self) -> Self {
+        expand(&self)
+    }
+}
+
+impl<T: Expand> Expand for Option<T> {
+    fn expand(self) -> Self {
+        self.map(T::expand)
+    }
+}
+
+impl<T: Expand> Expand for Vec<T> {
+    fn expand(self) -> Self {
+        self.into_iter().map(T::expand).collect()
+    }
+}
+
+// Only the values are expanded, the keys are names:
+impl<K: Ord, T: Expand> Expand for BTreeMap<K, T> {
+    fn expand(self) -> Self {
+        self.into_iter().map(|(k, v)| (k, v.expand())).collect()
+    }
+}
+
+/// Deserializes a field and expands its strings. Only the fields which hold paths (or
+/// commands) use it, so a `$` in a URL or a pattern stays what it is:
+///
+/// `#[serde(default, deserialize_with = "common::expanded")]`
+pub fn expanded<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Expand,
+{
+    T::deserialize(deserializer).map(T::expand)
+}
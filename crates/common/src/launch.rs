@@ -0,0 +1,453 @@
+use freedesktop_desktop_entry::DesktopEntry;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    error::Error,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+// Only needed for recording the launches in the test harness:
+#[cfg(feature = "harness")]
+use std::{fs::OpenOptions, io::Write, path::Path};
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::wl_registry::WlRegistry,
+    Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols::xdg::activation::v1::client::{
+    xdg_activation_token_v1::{self, XdgActivationTokenV1},
+    xdg_activation_v1::XdgActivationV1,
+};
+
+// Used to make X11 startup ids unique when a plugin launches several programs:
+static LAUNCH_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// If this env variable holds a path, plugins built with the `harness` feature append their
+/// launches to that file as `Launch`es (one RON value per line) instead of spawning them, so
+/// selecting a match in a test doesn't actually power off the machine.
+pub const RECORD_VAR: &str = "ANYRUN_PLUGINS_RECORD_LAUNCHES";
+
+/// A launch which was recorded instead of spawned, see `RECORD_VAR`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Launch {
+    /// The full argv, including the scope wrapper and the prefix.
+    pub argv: Vec<String>,
+    /// Only the variables which were set on the launcher, not the activation tokens.
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+    pub app_id: Option<String>,
+}
+
+/// Everything the launched program needs to know in order to take focus when it starts.
+#[derive(Debug, Default, Clone)]
+pub struct Startup {
+    pub app_id: Option<String>,
+    /// The `StartupNotify` key. Only programs which set it understand `DESKTOP_STARTUP_ID`.
+    pub notify: bool,
+    /// The `StartupWMClass` key. If it is set, it is what the compositor will know the window as.
+    pub wm_class: Option<String>,
+}
+
+impl Startup {
+    pub fn new(app_id: &str) -> Self {
+        Startup {
+            app_id: Some(app_id.to_string()),
+            notify: true,
+            wm_class: None,
+        }
+    }
+
+    pub fn from_desktop_entry(de: &DesktopEntry) -> Self {
+        Startup {
+            app_id: Some(de.appid.to_string()),
+            notify: de.startup_notify(),
+            wm_class: de.startup_wm_class().map(str::to_string),
+        }
+    }
+
+    // The compositor matches the token against the app id of the window that gets
+    // mapped, which is the StartupWMClass if the entry has one:
+    fn window_app_id(&self) -> Option<&str> {
+        self.wm_class.as_deref().or(self.app_id.as_deref())
+    }
+
+    /// Returns the environment variables which have to be set for the launched program.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+
+        // A token that was generated just now is always preferred. The one that anyrun was
+        // started with might have been consumed already, so it is only used as a fallback:
+        let token = wayland_activation_token(self.window_app_id())
+            .inspect_err(|e| {
+                if env::var_os("WAYLAND_DISPLAY").is_some() {
+                    eprintln!("Failed while requesting an activation token:\n    {e}");
+                }
+            })
+            .ok()
+            .or_else(|| env::var("XDG_ACTIVATION_TOKEN").ok())
+            .or_else(|| env::var("DESKTOP_STARTUP_ID").ok());
+
+        match token {
+            Some(token) => {
+                // Programs running through XWayland only know about DESKTOP_STARTUP_ID, and
+                // compositors accept the activation token there as well:
+                if self.notify {
+                    vars.push(("DESKTOP_STARTUP_ID", token.clone()));
+                }
+                vars.push(("XDG_ACTIVATION_TOKEN", token));
+            }
+            None if self.notify && env::var_os("DISPLAY").is_some() => {
+                vars.push(("DESKTOP_STARTUP_ID", self.x11_startup_id()));
+            }
+            None => (),
+        }
+
+        vars
+    }
+
+    // Follows the format from the startup notification spec:
+    // <launcher>-<launcher pid>-<hostname>-<program>-<sequence number>_TIME<timestamp>
+    fn x11_startup_id(&self) -> String {
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+        let program = self.app_id.as_deref().unwrap_or("unknown");
+        let sequence = LAUNCH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u32)
+            .unwrap_or_default();
+        format!(
+            "anyrun-plugins-{}-{}-{program}-{sequence}_TIME{time}",
+            std::process::id(),
+            hostname.trim()
+        )
+    }
+}
+
+/// Builds and spawns every command that the plugins launch, so prefix args, scopes,
+/// activation tokens and stdio are handled the same way everywhere.
+#[derive(Debug, Clone)]
+pub struct Launcher {
+    program: String,
+    args: Vec<OsString>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+    prefix: Vec<String>,
+    scope: bool,
+    detach: bool,
+    log: bool,
+    startup: Startup,
+}
+
+impl Launcher {
+    pub fn new(program: &str) -> Self {
+        Launcher {
+            program: program.to_string(),
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            prefix: Vec::new(),
+            scope: false,
+            detach: true,
+            log: false,
+            startup: Startup::default(),
+        }
+    }
+
+    // Arguments are often paths, which don't have to be valid UTF-8:
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<S: AsRef<OsStr>>(mut self, args: &[S]) -> Self {
+        self.args
+            .extend(args.iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn current_dir(mut self, cwd: Option<&str>) -> Self {
+        self.cwd = cwd.map(PathBuf::from);
+        self
+    }
+
+    /// Arguments that are put in front of the program, e.g. `["uwsm", "app", "--"]`.
+    pub fn prefix(mut self, prefix: &[String]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    /// Wraps the command in a transient systemd scope, so the program doesn't end up in
+    /// anyrun's cgroup (and doesn't get killed together with it).
+    pub fn scope(mut self, scope: bool) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Detached programs are daemonized: they get their own session, their stdio is connected
+    /// to /dev/null and they are reparented to init, so closing anyrun neither leaves zombies
+    /// behind nor sends SIGHUP to them. This is the default.
+    pub fn detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// Redirects stdout and stderr of the program to a new log file in the state directory.
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub fn startup(mut self, startup: Startup) -> Self {
+        self.startup = startup;
+        self
+    }
+
+    // Names the scope the way the systemd desktop environment conventions ask
+    // launchers to: app-<launcher>-<application id>-<random>.scope
+    fn scope_unit(&self) -> String {
+        let app_id = self.startup.app_id.as_deref().unwrap_or_else(|| {
+            self.program
+                .rsplit('/')
+                .next()
+                .unwrap_or(self.program.as_str())
+        });
+        let escaped: String = app_id
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | ':' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        let random = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default()
+            ^ std::process::id();
+        format!("app-anyrun-{escaped}-{random:x}.scope")
+    }
+
+    /// Returns the full argv, including the scope wrapper and the prefix. Arguments which
+    /// aren't valid UTF-8 are converted lossily, so this is only meant for showing the command.
+    pub fn argv(&self) -> Vec<String> {
+        self.os_argv()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn os_argv(&self) -> Vec<OsString> {
+        let mut argv: Vec<OsString> = Vec::new();
+        if self.scope {
+            argv.extend(
+                [
+                    "systemd-run",
+                    "--user",
+                    "--scope",
+                    "--quiet",
+                    "--collect",
+                    &format!("--unit={}", self.scope_unit()),
+                    "--",
+                ]
+                .map(OsString::from),
+            );
+        }
+        argv.extend(self.prefix.iter().map(OsString::from));
+        argv.push(OsString::from(&self.program));
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+
+    // Every launch gets its own file, named after the program and the time it was launched at:
+    fn log_file(&self) -> Result<File, Box<dyn Error>> {
+        let dir = crate::xdg::state_dir()?.join("logs");
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed while creating the log directory {dir:?}:\n    {e}"))?;
+        let program = self.program.rsplit('/').next().unwrap_or_default();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{program}-{time}.log"));
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed while creating the log file {path:?}:\n    {e}"))?;
+        Ok(file)
+    }
+
+    pub fn command(&self) -> Result<Command, Box<dyn Error>> {
+        let argv = self.os_argv();
+        let mut command = Command::new(&argv[0]);
+        command
+            .args(&argv[1..])
+            // Anyrun's own token must never leak into the child:
+            .env_remove("XDG_ACTIVATION_TOKEN")
+            .env_remove("DESKTOP_STARTUP_ID")
+            .envs(self.startup.env())
+            .envs(self.env.iter().map(|(k, v)| (k, v)));
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        if self.log {
+            let file = self.log_file()?;
+            command.stdout(file.try_clone()?).stderr(file);
+        } else if self.detach {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+
+        if self.detach {
+            command.stdin(Stdio::null());
+            // SAFETY: Only fork(), setsid() and _exit() are called between fork and exec,
+            // all of which are async-signal-safe.
+            unsafe {
+                command.pre_exec(daemonize);
+            }
+        }
+
+        Ok(command)
+    }
+
+    pub fn spawn(self) -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "harness")]
+        if let Some(path) = env::var_os(RECORD_VAR) {
+            return self.record(Path::new(&path));
+        }
+
+        let mut child = self.command()?.spawn().map_err(|e| {
+            format!(
+                "Failed while spawning {:?}:\n    {e}",
+                self.argv().join(" ")
+            )
+        })?;
+
+        // A detached child is only the intermediate process of the double fork, which exits
+        // right away, so waiting here is instant and makes sure it is reaped. Any other child
+        // is reaped in the background, so it doesn't become a zombie when it exits:
+        if self.detach {
+            child.wait()?;
+        } else {
+            thread::spawn(move || child.wait());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "harness")]
+    fn record(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let launch = Launch {
+            argv: self.argv(),
+            env: self.env.clone(),
+            cwd: self.cwd.clone(),
+            app_id: self.startup.app_id.clone(),
+        };
+        let line = ron::to_string(&launch)
+            .map_err(|e| format!("Failed while serializing the launch:\n    {e}"))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed while opening the launch record {path:?}:\n    {e}"))?;
+        writeln!(file, "{line}")
+            .map_err(|e| format!("Failed while recording the launch to {path:?}:\n    {e}"))?;
+        Ok(())
+    }
+}
+
+// Runs in the forked child right before exec. The child forks once more and exits, so the
+// grandchild (which goes on to exec the program) is reparented to init or to the nearest
+// subreaper. Then it starts a new session, so it doesn't receive anyrun's SIGHUP. If exec
+// fails, std still reports the error to the parent, because the grandchild inherits the pipe
+// that is used for that.
+fn daemonize() -> io::Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => (),
+        _ => unsafe { libc::_exit(0) },
+    }
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct TokenState {
+    token: Option<String>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for TokenState {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as wayland_client::Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<XdgActivationV1, ()> for TokenState {
+    fn event(
+        _: &mut Self,
+        _: &XdgActivationV1,
+        _: <XdgActivationV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<XdgActivationTokenV1, ()> for TokenState {
+    fn event(
+        state: &mut Self,
+        _: &XdgActivationTokenV1,
+        event: xdg_activation_token_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_activation_token_v1::Event::Done { token } = event {
+            state.token = Some(token);
+        }
+    }
+}
+
+// Asks the compositor for a fresh token through the xdg-activation protocol. Plugins don't
+// have access to anyrun's surface, so the compositor decides on its own whether the token
+// is allowed to steal focus. Most of them allow it while the launcher has keyboard focus.
+fn wayland_activation_token(app_id: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<TokenState>(&conn)?;
+    let qh = queue.handle();
+    let activation: XdgActivationV1 = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|e| format!("The compositor doesn't support xdg-activation:\n    {e}"))?;
+
+    let request = activation.get_activation_token(&qh, ());
+    if let Some(app_id) = app_id {
+        request.set_app_id(app_id.to_string());
+    }
+    request.commit();
+
+    let mut state = TokenState::default();
+    while state.token.is_none() {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    request.destroy();
+    activation.destroy();
+
+    // It is safe to unwrap here because of the loop above.
+    Ok(state.token.unwrap())
+}
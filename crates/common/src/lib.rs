@@ -1,13 +1,42 @@
-use serde::{de::DeserializeOwned, Deserialize};
-use std::{
-    env,
-    error::Error,
-    fmt::Debug,
-    fs::{self},
-    process::Command,
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt::Debug;
+
+mod browser;
+mod config_file;
+mod database;
+pub mod diagnostic;
+mod expand;
+pub mod launch;
+pub mod matching;
+mod mimeapps;
+pub mod pango;
+pub mod source;
+pub mod xdg;
+
+pub use browser::{
+    browser, open_mode, Browser, BrowserConfig, BrowserKind, Chromium, Firefox, KnownBrowser,
+    OpenMode,
+};
+pub use config_file::{template, ConfigFile, INIT_MARKER_VAR};
+pub use database::DatabaseCopy;
+pub use diagnostic::Fallible;
+pub use expand::{expand, expanded, Expand};
+pub use matching::{Matcher, MatchingConfig};
+pub use mimeapps::{default_browser, find_desktop_file};
+pub use source::{ItemSource, SourceConfig};
+
+static DEFAULT_BROWSER_CONFIG: BrowserConfig = BrowserConfig {
+    kind: None,
+    binary: None,
+    profile: None,
+    args: None,
+    open_mode: None,
+    modifiers: None,
 };
 
-#[derive(Debug, Deserialize)]
+/// What is shown on an empty input: every match, no matches or only the listed ones.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub enum Bib {
     All,
     None,
@@ -15,215 +44,69 @@ pub enum Bib {
     // TODO 3.
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct CommonConfig {
+    /// The browser used by the browser plugins. Every field can be overridden in the
+    /// plugins' own config files.
+    browser: Option<BrowserConfig>,
+    /// The old name of browser.profile, which is still read if that one isn't set.
     browser_profile_name: Option<String>,
+    /// Put in front of every launched command, e.g. ["uwsm", "app", "--"].
+    #[serde(default, deserialize_with = "crate::expanded")]
     prefix_args: Option<Vec<String>>,
+    /// Launch every command in its own transient systemd scope.
+    systemd_scope: Option<bool>,
+    /// Write the output of launched programs to $XDG_STATE_HOME/anyrun-plugins/logs.
+    log_output: Option<bool>,
 }
 
 impl CommonConfig {
+    pub fn browser(&self) -> &BrowserConfig {
+        self.browser.as_ref().unwrap_or(&DEFAULT_BROWSER_CONFIG)
+    }
     pub fn browser_profile_name(&self) -> &str {
         self.browser_profile_name.as_deref().unwrap_or("default")
     }
     pub fn prefix_args(&self) -> &[String] {
         self.prefix_args.as_deref().unwrap_or_default()
     }
+    pub fn systemd_scope(&self) -> bool {
+        self.systemd_scope.unwrap_or(false)
+    }
+    pub fn log_output(&self) -> bool {
+        self.log_output.unwrap_or(false)
+    }
+    // Every plugin launches its commands through this, so the prefix args and the scope
+    // are applied the same way everywhere:
+    pub fn launcher(&self, program: &str) -> launch::Launcher {
+        launch::Launcher::new(program)
+            .prefix(self.prefix_args())
+            .scope(self.systemd_scope())
+            .log(self.log_output())
+    }
 }
 
 impl Default for CommonConfig {
     fn default() -> Self {
         CommonConfig {
+            browser: Some(BrowserConfig::default()),
             browser_profile_name: Some("default".to_string()),
             prefix_args: Some(Vec::default()),
+            systemd_scope: Some(false),
+            log_output: Some(false),
         }
     }
 }
 
-pub fn common_config(config_dir: &str, plugin: &str) -> CommonConfig {
-    match fs::read_to_string(format!("{config_dir}/Common.ron")) {
-        Ok(s) => ron::from_str(&s)
-            .map_err(|e| {
-                format!(
-                    "({plugin}) Failed while parsing common config file. \
-                Falling back to default...\n  {e}"
-                )
-            })
-            .unwrap_or_default(),
-        Err(e) => {
-            eprintln!(
-                "({plugin}) Failed while reading common config file. Falling back to default...\n  {e}"
-            );
-            CommonConfig::default()
-        }
-    }
+pub fn common_config(config_dir: &str, plugin: &str) -> ConfigFile<CommonConfig> {
+    ConfigFile::load(config_dir, plugin, "Common.ron")
 }
 
-pub fn config<T>(config_dir: &str, plugin: &str) -> T
+pub fn config<T>(config_dir: &str, plugin: &str) -> ConfigFile<T>
 where
-    T: DeserializeOwned + Default + Debug,
+    T: DeserializeOwned + Serialize + Default + Debug,
 {
-    match fs::read_to_string(format!("{config_dir}/{plugin}.ron")) {
-        Ok(s) => ron::from_str(&s)
-            .map_err(|e| {
-                format!(
-                "({plugin}) Failed while parsing config file. Falling back to default...:\n  {e}"
-            )
-            })
-            .unwrap_or_default(),
-        Err(e) => {
-            eprintln!(
-                "({plugin}) Failed while reading config file. Falling back to default...\n  {e}"
-            );
-            T::default()
-        }
-    }
-}
-
-// The Send + Sync are necessary for creating structs:
-pub trait Browser: Send + Sync + Debug {
-    fn new_window(&self, url: &str, prefix: &[String]) -> Result<(), Box<dyn Error>>;
-}
-
-#[derive(Debug)]
-pub struct Firefox {
-    profile_name: String,
-}
-
-impl Firefox {
-    pub fn new(profile_name: &str) -> Self {
-        Firefox {
-            profile_name: profile_name.to_string(),
-        }
-    }
-    pub fn profile_dir(profile_name: &str) -> Result<String, Box<dyn Error>> {
-        let home_dir = env::var("HOME").map_err(|e| {
-            format!("HOME env variable not set! Bookmarks cannot be obtained:\n    {e}")
-        })?;
-
-        let firefox_path = format!("{home_dir}/.mozilla/firefox");
-
-        // The profile directory may have a name like "<random_characters>.<name>"
-        // or just "<name>". Because it cannot be inferred from the profile name alone,
-        // a .find() method is required to find the target dir:
-        let profile_dirname = fs::read_dir(&firefox_path)
-            .map_err(|e| format!("Failed while reading firefox directory:\n    {e}"))?
-            // At this point we have an Iterator over Result<DirEntry, Error> and
-            // we want the DirEntry which corresponds to the profile directory:
-            .find_map(|entry| match entry {
-                Ok(dir_entry) => {
-                    if dir_entry
-                        .file_name()
-                        .to_string_lossy()
-                        .contains(profile_name)
-                    {
-                        Some(dir_entry)
-                    } else {
-                        None
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed while unwrapping entry of {firefox_path}:\n    {e}");
-                    None
-                }
-            })
-            // At this point we either have the DirEntry we need or we don't. We have to
-            // return from the function with an Err if we didn't find the directory:
-            .ok_or_else(|| {
-                format!(
-                    "Cannot find the profile directory, please make sure that \
-                    the profile with name {profile_name} exists!"
-                )
-            })?
-            // In this part of the code we are converting DirEntry to String:
-            .file_name()
-            .to_string_lossy()
-            .into_owned();
-
-        Ok(format!("{firefox_path}/{profile_dirname}"))
-    }
-
-    pub fn profile_dir2(&self) -> Result<String, Box<dyn Error>> {
-        let home_dir = env::var("HOME").map_err(|e| {
-            format!("HOME env variable not set! Bookmarks cannot be obtained:\n    {e}")
-        })?;
-
-        let firefox_path = format!("{home_dir}/.mozilla/firefox");
-
-        // The profile directory may have a name like "<random_characters>.<name>"
-        // or just "<name>". Because it cannot be inferred from the profile name alone,
-        // a .find() method is required to find the target dir:
-        let profile_dirname = fs::read_dir(&firefox_path)
-            .map_err(|e| format!("Failed while reading firefox directory:\n    {e}"))?
-            // At this point we have an Iterator over Result<DirEntry, Error> and
-            // we want the DirEntry which corresponds to the profile directory:
-            .find_map(|entry| match entry {
-                Ok(dir_entry) => {
-                    if dir_entry
-                        .file_name()
-                        .to_string_lossy()
-                        .contains(&self.profile_name)
-                    {
-                        Some(dir_entry)
-                    } else {
-                        None
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed while unwrapping entry of {firefox_path}:\n    {e}");
-                    None
-                }
-            })
-            // At this point we either have the DirEntry we need or we don't. We have to
-            // return from the function with an Err if we didn't find the directory:
-            .ok_or_else(|| {
-                format!(
-                    "Cannot find the profile directory, please make sure that \
-                    the profile with name {} exists!",
-                    self.profile_name
-                )
-            })?
-            // In this part of the code we are converting DirEntry to String:
-            .file_name()
-            .to_string_lossy()
-            .into_owned();
-
-        Ok(format!("{firefox_path}/{profile_dirname}"))
-    }
-}
-
-impl Browser for Firefox {
-    fn new_window(&self, url: &str, prefix_args: &[String]) -> Result<(), Box<dyn Error>> {
-        if prefix_args.is_empty() {
-            Command::new("firefox")
-                .args(["--new-window", url])
-                .spawn()?;
-        } else {
-            Command::new(&prefix_args[0])
-                .args(prefix_args[1..].iter())
-                .args(["firefox", "--new-window", url])
-                .spawn()?;
-        }
-        Ok(())
-    }
-}
-
-pub fn default_browser_id() -> Result<String, Box<dyn Error>> {
-    let output = Command::new("xdg-settings")
-        .args(["get", "default-web-browser"])
-        .output()
-        .map_err(|e| format!("Failed while executing \"xdg-settings\":\n   {e}"))?;
-    let appid = String::from_utf8(output.stdout)
-        .map_err(|e| {
-            format!("Failed while converting \"xdg-settings\" stdout to a String:\n   {e}")
-        })?
-        .trim()
-        .replace(".desktop", "");
-
-    // match appid.as_str() {
-    //     "firefox" => Ok(Box::new(Firefox::new())),
-    //     _ => Err("Unsupported default browser!".into()),
-    // }
-    Ok(appid)
+    ConfigFile::load(config_dir, plugin, &format!("{plugin}.ron"))
 }
 
 // Utility function for extracting the domain from a URL:
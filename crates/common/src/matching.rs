@@ -0,0 +1,247 @@
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use nucleo_matcher::{
+    chars,
+    pattern::{Atom, AtomKind, CaseMatching, Normalization},
+    Utf32Str,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::pango;
+
+/// How the input is matched against the titles of the matches.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Fuzzy matching like skim's, which is what the plugins have always used.
+    Skim,
+    /// Fuzzy matching like fzf's, which prefers matches at word boundaries.
+    Nucleo,
+    /// The title has to start with the input.
+    Prefix,
+    /// The title has to contain the input.
+    Substring,
+    /// The title has to be the input.
+    Exact,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// Case is ignored, unless the input has an uppercase letter in it.
+    Smart,
+    Ignore,
+    Respect,
+}
+
+/// The matching settings of a plugin. Every plugin which matches titles has a `matching`
+/// field with these in its config file.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct MatchingConfig {
+    /// Skim, Nucleo, Prefix, Substring or Exact.
+    algorithm: Option<Algorithm>,
+    /// Smart, Ignore or Respect.
+    case: Option<Case>,
+    /// Let "e" in the input match "é" in a title. It is only done while the input doesn't
+    /// have any letters with diacritics itself.
+    fold_diacritics: Option<bool>,
+    /// Matches which score lower than this are dropped. The scores of different algorithms
+    /// aren't comparable, so this has to be tuned together with the algorithm.
+    min_score: Option<i64>,
+    /// Show which characters of the titles were matched.
+    highlight: Option<bool>,
+    /// The color of the matched characters, e.g. "#ff8800" or "orange". If it isn't set,
+    /// they are made bold instead.
+    highlight_color: Option<String>,
+}
+
+impl MatchingConfig {
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm.unwrap_or(Algorithm::Skim)
+    }
+    pub fn case(&self) -> Case {
+        self.case.unwrap_or(Case::Smart)
+    }
+    pub fn fold_diacritics(&self) -> bool {
+        self.fold_diacritics.unwrap_or(false)
+    }
+    pub fn min_score(&self) -> Option<i64> {
+        self.min_score
+    }
+    pub fn highlight(&self) -> bool {
+        self.highlight.unwrap_or(false)
+    }
+    pub fn highlight_color(&self) -> Option<&str> {
+        self.highlight_color.as_deref()
+    }
+    pub fn matcher(&self) -> Matcher {
+        Matcher {
+            algorithm: self.algorithm(),
+            case: self.case(),
+            fold_diacritics: self.fold_diacritics(),
+            min_score: self.min_score(),
+            highlight: self.highlight(),
+            highlight_color: self.highlight_color().map(str::to_string),
+            skim: None,
+            nucleo: nucleo_matcher::Matcher::new(nucleo_matcher::Config::DEFAULT),
+            atom: None,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        MatchingConfig {
+            algorithm: Some(Algorithm::Skim),
+            case: Some(Case::Smart),
+            fold_diacritics: Some(false),
+            min_score: None,
+            highlight: Some(false),
+            highlight_color: None,
+        }
+    }
+}
+
+/// Scores titles against the input the way a `MatchingConfig` says. It holds the buffers of
+/// the matchers, so it should be created once per query and not once per title.
+pub struct Matcher {
+    algorithm: Algorithm,
+    case: Case,
+    fold_diacritics: bool,
+    min_score: Option<i64>,
+    highlight: bool,
+    highlight_color: Option<String>,
+    // The skim matcher is only built once it is needed, since it doesn't come for free:
+    skim: Option<SkimMatcherV2>,
+    nucleo: nucleo_matcher::Matcher,
+    // The input is the same for every title, so it is only turned into an atom once:
+    atom: Option<(String, Atom)>,
+    buf: Vec<char>,
+}
+
+impl Matcher {
+    /// Gives a bonus to matches right after a "/", for titles which are paths.
+    pub fn paths(mut self) -> Self {
+        self.nucleo = nucleo_matcher::Matcher::new(nucleo_matcher::Config::DEFAULT.match_paths());
+        self
+    }
+
+    /// The score of `title` for `input`, or `None` if it doesn't match (or if it scores lower
+    /// than the minimum score). Higher is better.
+    pub fn score(&mut self, title: &str, input: &str) -> Option<i64> {
+        self.indices(title, input).map(|(score, _)| score)
+    }
+
+    /// Like `score`, but it also returns which characters of `title` were matched.
+    pub fn indices(&mut self, title: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        let (score, indices) = match self.algorithm {
+            Algorithm::Skim => self.skim_indices(title, input),
+            Algorithm::Nucleo => self.nucleo_indices(title, input, AtomKind::Fuzzy),
+            Algorithm::Prefix => self.nucleo_indices(title, input, AtomKind::Prefix),
+            Algorithm::Substring => self.nucleo_indices(title, input, AtomKind::Substring),
+            Algorithm::Exact => self.nucleo_indices(title, input, AtomKind::Exact),
+        }?;
+        match self.min_score {
+            Some(min_score) if score < min_score => None,
+            _ => Some((score, indices)),
+        }
+    }
+
+    /// Whether the matches have to be rendered with Pango, i.e. whether highlighting is on.
+    pub fn use_pango(&self) -> bool {
+        self.highlight
+    }
+
+    /// `text` with the characters matching `input` highlighted, if highlighting is on.
+    pub fn highlight(&mut self, text: &str, input: &str) -> String {
+        self.highlight_parts(&[text], input).remove(0)
+    }
+
+    /// Matches `input` against all the parts put together and highlights every part on its
+    /// own, e.g. for a path whose directory and file name are shown in different places.
+    pub fn highlight_parts(&mut self, parts: &[&str], input: &str) -> Vec<String> {
+        if !self.highlight {
+            return parts.iter().map(|part| part.to_string()).collect();
+        }
+        let (_, indices) = self.indices(&parts.concat(), input).unwrap_or_default();
+
+        let mut offset = 0;
+        parts
+            .iter()
+            .map(|part| {
+                let len = part.chars().count();
+                let part_indices: Vec<usize> = indices
+                    .iter()
+                    .filter(|i| (offset..offset + len).contains(i))
+                    .map(|i| i - offset)
+                    .collect();
+                offset += len;
+                pango::highlight(part, &part_indices, self.highlight_color.as_deref())
+            })
+            .collect()
+    }
+
+    /// Makes `text` safe to put next to highlighted text, if highlighting is on.
+    pub fn escape(&self, text: &str) -> String {
+        match self.highlight {
+            true => pango::escape(text),
+            false => text.to_string(),
+        }
+    }
+
+    fn skim_indices(&mut self, title: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        let case = self.case;
+        let skim = self.skim.get_or_insert_with(|| match case {
+            Case::Smart => SkimMatcherV2::default().smart_case(),
+            Case::Ignore => SkimMatcherV2::default().ignore_case(),
+            Case::Respect => SkimMatcherV2::default().respect_case(),
+        });
+        // Skim can't fold diacritics by itself, so the title is folded before it gets there.
+        // Folding keeps every character where it is, so the indices still fit the title.
+        // Just like with nucleo, an input with diacritics turns the folding off:
+        if self.fold_diacritics && input.chars().all(|c| chars::normalize(c) == c) {
+            let title: String = title.chars().map(chars::normalize).collect();
+            skim.fuzzy_indices(&title, input)
+        } else {
+            skim.fuzzy_indices(title, input)
+        }
+    }
+
+    fn nucleo_indices(
+        &mut self,
+        title: &str,
+        input: &str,
+        kind: AtomKind,
+    ) -> Option<(i64, Vec<usize>)> {
+        let case = match self.case {
+            Case::Smart => CaseMatching::Smart,
+            Case::Ignore => CaseMatching::Ignore,
+            Case::Respect => CaseMatching::Respect,
+        };
+        let normalization = match self.fold_diacritics {
+            true => Normalization::Smart,
+            false => Normalization::Never,
+        };
+        if !matches!(&self.atom, Some((cached, _)) if cached == input) {
+            let atom = Atom::new(input, case, normalization, kind, false);
+            self.atom = Some((input.to_string(), atom));
+        }
+        // It is safe to unwrap here, because the atom was just set:
+        let (_, atom) = self.atom.as_ref().unwrap();
+
+        // Utf32Str::new() would merge graphemes into single characters, which would make the
+        // indices point to the wrong characters, so the title is split into chars here:
+        let haystack = if title.is_ascii() {
+            Utf32Str::Ascii(title.as_bytes())
+        } else {
+            self.buf.clear();
+            self.buf.extend(title.chars());
+            Utf32Str::Unicode(&self.buf)
+        };
+        let mut indices = Vec::new();
+        let score = atom.indices(haystack, &mut self.nucleo, &mut indices)?;
+        Some((
+            i64::from(score),
+            indices.into_iter().map(|i| i as usize).collect(),
+        ))
+    }
+}
@@ -0,0 +1,154 @@
+use crate::xdg::{config_dirs, config_home, data_dirs, data_home};
+use freedesktop_desktop_entry::DesktopEntry;
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// The browser is whatever handles https links. Not every mimeapps.list sets that, so
+// the scheme handler for http and the handler for HTML files are tried as well:
+const BROWSER_MIME_TYPES: &[&str] = &[
+    "x-scheme-handler/https",
+    "x-scheme-handler/http",
+    "text/html",
+];
+
+// The mimeapps.list files in the order the spec says they have to be looked at. In every
+// directory the desktop specific files (like "gnome-mimeapps.list") come first:
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let desktops: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let config_dirs = config_home().into_iter().chain(config_dirs());
+    // Looking for mimeapps.list in the applications directories is deprecated, but
+    // some distributions still ship their defaults there:
+    let data_dirs = data_home()
+        .into_iter()
+        .chain(data_dirs())
+        .map(|dir| dir.join("applications"));
+
+    config_dirs
+        .chain(data_dirs)
+        .flat_map(|dir| {
+            desktops
+                .iter()
+                .map(|desktop| dir.join(format!("{desktop}-mimeapps.list")))
+                .chain([dir.join("mimeapps.list")])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Returns the desktop file IDs from the "Default Applications" group of a mimeapps.list:
+fn default_applications(mimeapps: &str, mime_type: &str) -> Vec<String> {
+    let mut in_defaults = false;
+    for line in mimeapps.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_defaults = line == "[Default Applications]";
+            continue;
+        }
+        if !in_defaults {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == mime_type {
+                return value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Finds the .desktop file with the given desktop file ID. A dash in the ID can stand
+/// for a subdirectory, e.g. "kde-konqueror.desktop" can be "kde/konqueror.desktop".
+pub fn find_desktop_file(id: &str) -> Option<PathBuf> {
+    let dirs = data_home()
+        .into_iter()
+        .chain(data_dirs())
+        .map(|dir| dir.join("applications"));
+
+    for dir in dirs {
+        let candidates = [id.to_string()].into_iter().chain(
+            id.match_indices('-')
+                .map(|(i, _)| format!("{}/{}", &id[..i], &id[i + 1..])),
+        );
+        for candidate in candidates {
+            let path = dir.join(candidate);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+fn parse_desktop_file(path: &Path) -> Result<DesktopEntry<'static>, Box<dyn Error>> {
+    DesktopEntry::from_path(path.to_path_buf(), None::<&[&str]>)
+        .map_err(|e| format!("Failed while parsing {path:?} to a Desktop Entry:\n    {e}").into())
+}
+
+fn resolve_from_mimeapps() -> Result<DesktopEntry<'static>, Box<dyn Error>> {
+    let lists: Vec<(PathBuf, String)> = mimeapps_lists()
+        .into_iter()
+        .filter_map(|path| fs::read_to_string(&path).ok().map(|s| (path, s)))
+        .collect();
+
+    for mime_type in BROWSER_MIME_TYPES {
+        for (path, mimeapps) in &lists {
+            // The first listed application which is actually installed wins:
+            for id in default_applications(mimeapps, mime_type) {
+                match find_desktop_file(&id) {
+                    Some(desktop_file) => return parse_desktop_file(&desktop_file),
+                    None => eprintln!(
+                        "{path:?} sets {id} as the default for {mime_type}, but it isn't installed. Skipping..."
+                    ),
+                }
+            }
+        }
+    }
+
+    Err("None of the mimeapps.list files set a default browser!".into())
+}
+
+fn resolve_from_xdg_settings() -> Result<DesktopEntry<'static>, Box<dyn Error>> {
+    let output = Command::new("xdg-settings")
+        .args(["get", "default-web-browser"])
+        .output()
+        .map_err(|e| format!("Failed while executing \"xdg-settings\":\n   {e}"))?;
+    let id = String::from_utf8(output.stdout)
+        .map_err(|e| {
+            format!("Failed while converting \"xdg-settings\" stdout to a String:\n   {e}")
+        })?
+        .trim()
+        .to_string();
+
+    let desktop_file =
+        find_desktop_file(&id).ok_or_else(|| format!("Cannot find the desktop file for {id}!"))?;
+    parse_desktop_file(&desktop_file)
+}
+
+/// Returns the Desktop Entry of the default browser. It is read from the mimeapps.list files
+/// directly, and `xdg-settings` is only asked if that fails and it happens to be installed.
+pub fn default_browser() -> Result<DesktopEntry<'static>, Box<dyn Error>> {
+    resolve_from_mimeapps().or_else(|mimeapps_err| {
+        resolve_from_xdg_settings().map_err(|xdg_settings_err| {
+            format!(
+                "Failed while finding the default browser:\n    {mimeapps_err}\n    \
+                And \"xdg-settings\" didn't help either:\n    {xdg_settings_err}"
+            )
+            .into()
+        })
+    })
+}
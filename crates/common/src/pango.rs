@@ -0,0 +1,46 @@
+/// Escapes the characters which have a meaning in Pango markup. Every string which doesn't
+/// come from the plugin itself has to go through this when a match uses Pango.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&#39;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes `s` and marks the characters at `indices` (char indices, not byte indices). They
+/// are made bold, or colored if a `color` (like "#ff8800" or "orange") is given.
+pub fn highlight(s: &str, indices: &[usize], color: Option<&str>) -> String {
+    let (open, close) = match color {
+        Some(color) => (
+            format!("<span foreground=\"{}\">", escape(color)),
+            "</span>",
+        ),
+        None => (String::from("<b>"), "</b>"),
+    };
+
+    let mut markup = String::with_capacity(s.len());
+    let mut open_span = false;
+    for (i, c) in s.chars().enumerate() {
+        // Neighbouring characters share a span, so the markup doesn't blow up:
+        let highlighted = indices.contains(&i);
+        if highlighted && !open_span {
+            markup.push_str(&open);
+        } else if !highlighted && open_span {
+            markup.push_str(close);
+        }
+        open_span = highlighted;
+        markup.push_str(&escape(c.encode_utf8(&mut [0; 4])));
+    }
+    if open_span {
+        markup.push_str(close);
+    }
+    markup
+}
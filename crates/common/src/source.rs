@@ -0,0 +1,137 @@
+use abi_stable::std_types::{
+    ROption::{RNone, RSome},
+    RString, RVec,
+};
+use anyrun_plugin::{HandleResult, Match};
+use std::{borrow::Cow, cmp::Reverse};
+
+use crate::{Bib, CommonConfig, Matcher};
+
+/// The parts of a plugin's config that `matches()` needs.
+pub trait SourceConfig {
+    fn prefix(&self) -> &str;
+    fn bib(&self) -> &Bib;
+    /// How many items are shown on a blank input.
+    fn max_entries(&self) -> usize;
+    /// How many matches are shown for any other input. The same as max_entries by default.
+    fn max_matches(&self) -> usize {
+        self.max_entries()
+    }
+    fn matcher(&self) -> Matcher;
+}
+
+/// A list of things a plugin offers, like desktop entries or bookmarks. A plugin only has to
+/// say what its items look like and what selecting one does, and `matches()` and `handle()`
+/// do the rest.
+pub trait ItemSource {
+    type Item;
+
+    /// The items, in the order they are shown on a blank input. It has to be the same list
+    /// in `matches()` and `handle()`, because matches only hold the index of their item.
+    fn items(&self) -> &[Self::Item];
+    /// What the input is matched against, unless `matched` says otherwise.
+    fn title<'a>(&'a self, item: &'a Self::Item) -> Cow<'a, str>;
+    fn description<'a>(&'a self, _item: &'a Self::Item) -> Option<Cow<'a, str>> {
+        None
+    }
+    fn icon<'a>(&'a self, _item: &'a Self::Item) -> Option<Cow<'a, str>> {
+        None
+    }
+    /// What the names in `Bib::Currated` are compared with. The title by default.
+    fn key<'a>(&'a self, item: &'a Self::Item) -> Cow<'a, str> {
+        self.title(item)
+    }
+    /// What the input is matched against, in parts which are highlighted on their own. The
+    /// last part is shown as the title and, if there are several, the first one as the
+    /// description, e.g. `["/home/me", "/", "notes.md"]` for matching a file by its path.
+    /// The title alone by default.
+    fn matched<'a>(&'a self, item: &'a Self::Item) -> Vec<Cow<'a, str>> {
+        vec![self.title(item)]
+    }
+    fn activate(&self, item: &Self::Item, common_config: &CommonConfig) -> HandleResult;
+}
+
+/// The matches of `source` for `input`: the prefix is checked and stripped, a blank input is
+/// handled as `bib` says, and anything else is matched, ranked and cut to `max_matches`.
+pub fn matches<S: ItemSource>(source: &S, config: &impl SourceConfig, input: &str) -> RVec<Match> {
+    // Early return for the wrong prefix:
+    let Some(stripped_input) = input.strip_prefix(config.prefix()) else {
+        return RVec::new();
+    };
+    let stripped_input = stripped_input.trim();
+    let items = source.items().iter().enumerate();
+
+    // Handling blank input:
+    if stripped_input.is_empty() {
+        let shown: Vec<(usize, &S::Item)> = match config.bib() {
+            Bib::All => items.take(config.max_entries()).collect(),
+            Bib::None => Vec::new(),
+            Bib::Currated(v) => items
+                .filter(|(_, item)| v.iter().any(|key| *key == source.key(item)))
+                .take(config.max_entries())
+                .collect(),
+        };
+        return RVec::from_iter(shown.into_iter().map(|(i, item)| Match {
+            title: RString::from(&*source.title(item)),
+            description: source.description(item).map(|d| RString::from(&*d)).into(),
+            use_pango: false,
+            icon: source.icon(item).map(|icon| RString::from(&*icon)).into(),
+            id: RSome(i as u64),
+        }));
+    }
+
+    let mut matcher = config.matcher();
+    let mut matched: Vec<(i64, usize, &S::Item)> = items
+        .filter_map(|(i, item)| {
+            let score = matcher.score(&source.matched(item).concat(), stripped_input)?;
+            Some((score, i, item))
+        })
+        .collect();
+    // Sorting by score in descending order. The sort is stable, so equally good
+    // matches stay in the order of the items:
+    matched.sort_by_key(|&(score, _, _)| Reverse(score));
+
+    RVec::from_iter(
+        matched
+            .into_iter()
+            .take(config.max_matches())
+            .map(|(_, i, item)| {
+                let matched = source.matched(item);
+                let parts: Vec<&str> = matched.iter().map(|part| &**part).collect();
+                let mut highlighted = matcher.highlight_parts(&parts, stripped_input);
+                let title = highlighted.pop().unwrap_or_default();
+                let description = match highlighted.into_iter().next() {
+                    Some(description) => Some(RString::from(description)),
+                    None => source
+                        .description(item)
+                        .map(|description| RString::from(matcher.escape(&description))),
+                };
+                Match {
+                    title: RString::from(title),
+                    description: description.into(),
+                    use_pango: matcher.use_pango(),
+                    icon: source.icon(item).map(|icon| RString::from(&*icon)).into(),
+                    id: RSome(i as u64),
+                }
+            }),
+    )
+}
+
+/// Activates the item of a match created by `matches()`.
+pub fn handle<S: ItemSource>(
+    source: &S,
+    selection: &Match,
+    common_config: &CommonConfig,
+) -> HandleResult {
+    let item = match selection.id {
+        RSome(id) => source.items().get(id as usize),
+        RNone => None,
+    };
+    match item {
+        Some(item) => source.activate(item, common_config),
+        None => {
+            eprintln!("(Common) Failed while finding the selected item. Closing...");
+            HandleResult::Close
+        }
+    }
+}
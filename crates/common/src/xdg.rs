@@ -0,0 +1,56 @@
+use std::{env, error::Error, path::PathBuf};
+
+fn home() -> Result<PathBuf, Box<dyn Error>> {
+    env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|e| format!("Failed while getting the HOME env variable:\n    {e}").into())
+}
+
+// Returns the value of an env variable which holds a directory, or the default if it is unset or empty:
+fn xdg_dir(
+    var: &str,
+    default: impl FnOnce() -> Result<PathBuf, Box<dyn Error>>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    match env::var(var) {
+        Ok(v) if !v.is_empty() => Ok(PathBuf::from(v)),
+        _ => default(),
+    }
+}
+
+fn xdg_dirs(var: &str, default: &str) -> Vec<PathBuf> {
+    env::var(var)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .as_deref()
+        .unwrap_or(default)
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+pub fn config_home() -> Result<PathBuf, Box<dyn Error>> {
+    xdg_dir("XDG_CONFIG_HOME", || Ok(home()?.join(".config")))
+}
+
+pub fn config_dirs() -> Vec<PathBuf> {
+    xdg_dirs("XDG_CONFIG_DIRS", "/etc/xdg")
+}
+
+pub fn data_home() -> Result<PathBuf, Box<dyn Error>> {
+    xdg_dir("XDG_DATA_HOME", || Ok(home()?.join(".local/share")))
+}
+
+pub fn data_dirs() -> Vec<PathBuf> {
+    xdg_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share")
+}
+
+// Files which have to survive a reboot but aren't config (like logs) go to $XDG_STATE_HOME:
+pub fn state_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_dir("XDG_STATE_HOME", || Ok(home()?.join(".local/state")))?.join("anyrun-plugins"))
+}
+
+// Files which can be made again at any time (like extracted icons) go to $XDG_CACHE_HOME:
+pub fn cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(xdg_dir("XDG_CACHE_HOME", || Ok(home()?.join(".cache")))?.join("anyrun-plugins"))
+}
@@ -0,0 +1,172 @@
+use common::{CommonConfig, ConfigFile};
+use ron::ser::PrettyConfig;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{env, error::Error, process};
+
+// The config structs are taken straight from the plugins' sources, so this binary can't
+// drift away from what the plugins actually read:
+#[allow(dead_code)]
+#[path = "../../applications/src/config.rs"]
+mod applications;
+#[allow(dead_code)]
+#[path = "../../bookmarks/src/config.rs"]
+mod bookmarks;
+#[allow(dead_code)]
+#[path = "../../kidex/src/config.rs"]
+mod kidex;
+#[allow(dead_code)]
+#[path = "../../powermenu/src/config.rs"]
+mod powermenu;
+#[allow(dead_code)]
+#[path = "../../rink/src/config.rs"]
+mod rink;
+#[allow(dead_code)]
+#[path = "../../shell/src/config.rs"]
+mod shell;
+#[allow(dead_code)]
+#[path = "../../webpages/src/config.rs"]
+mod webpages;
+#[allow(dead_code)]
+#[path = "../../websearch/src/config.rs"]
+mod websearch;
+
+const USAGE: &str = "Usage:
+    config-check [CONFIG_DIR]    Print the effective config of every plugin and the problems in the files
+    config-check schema NAME     Print the JSON Schema of a config file
+    config-check template NAME   Print a config file with every field set to its default
+
+CONFIG_DIR defaults to $XDG_CONFIG_HOME/anyrun and NAME is one of:
+    Common, Applications, Bookmarks, Kidex, Powermenu, Rink, Shell, Webpages, Websearch";
+
+type Check = fn(&str, &str, &str) -> Result<bool, Box<dyn Error>>;
+
+// Everything this binary does with a config file, for one config struct:
+struct Plugin {
+    name: &'static str,
+    check: Check,
+    schema: fn() -> RootSchema,
+    template: fn(&str) -> String,
+}
+
+impl Plugin {
+    fn new<T>(name: &'static str) -> Self
+    where
+        T: DeserializeOwned + Serialize + Default + JsonSchema,
+    {
+        Plugin {
+            name,
+            check: check::<T>,
+            schema: || schemars::schema_for!(T),
+            template: common::template::<T>,
+        }
+    }
+    fn file_name(&self) -> String {
+        match self.name {
+            // Kidex has always read a lowercase file name:
+            "Kidex" => "kidex.ron".to_string(),
+            _ => format!("{}.ron", self.name),
+        }
+    }
+    // The name which has to be in front of the parentheses for the file to be valid RON:
+    fn struct_name(&self) -> &str {
+        match self.name {
+            "Common" => "CommonConfig",
+            _ => "Config",
+        }
+    }
+}
+
+fn plugins() -> Vec<Plugin> {
+    vec![
+        Plugin::new::<CommonConfig>("Common"),
+        Plugin::new::<applications::Config>("Applications"),
+        Plugin::new::<bookmarks::Config>("Bookmarks"),
+        Plugin::new::<kidex::Config>("Kidex"),
+        Plugin::new::<powermenu::Config>("Powermenu"),
+        Plugin::new::<rink::Config>("Rink"),
+        Plugin::new::<shell::Config>("Shell"),
+        Plugin::new::<webpages::Config>("Webpages"),
+        Plugin::new::<websearch::Config>("Websearch"),
+    ]
+}
+
+// Loads the file the same way the plugins do and prints what they would end up with.
+// Returns whether the file has any problems:
+fn check<T>(config_dir: &str, name: &str, file_name: &str) -> Result<bool, Box<dyn Error>>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let config: ConfigFile<T> = ConfigFile::load_quietly(config_dir, name, file_name);
+    let pretty = PrettyConfig::new().struct_names(true);
+    let value = ron::ser::to_string_pretty(&*config, pretty)
+        .map_err(|e| format!("Failed while serializing the config:\n    {e}"))?;
+
+    println!("{value}");
+    for issue in config.issues() {
+        println!("    ! {issue}");
+    }
+    Ok(!config.issues().is_empty())
+}
+
+fn find(name: Option<String>) -> Result<Plugin, Box<dyn Error>> {
+    let name = name.ok_or(USAGE)?;
+    plugins()
+        .into_iter()
+        .find(|plugin| plugin.name.eq_ignore_ascii_case(&name))
+        .ok_or_else(|| format!("There is no config file called \"{name}\"\n\n{USAGE}").into())
+}
+
+fn run() -> Result<bool, Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(true)
+        }
+        Some("schema") => {
+            let plugin = find(args.next())?;
+            let schema = serde_json::to_string_pretty(&(plugin.schema)())
+                .map_err(|e| format!("Failed while serializing the schema:\n    {e}"))?;
+            println!("{schema}");
+            Ok(true)
+        }
+        Some("template") => {
+            let plugin = find(args.next())?;
+            println!("{}", (plugin.template)(plugin.struct_name()));
+            Ok(true)
+        }
+        config_dir => {
+            let config_dir = match config_dir {
+                Some(config_dir) => config_dir.to_string(),
+                None => common::xdg::config_home()
+                    .map_err(|e| format!("Failed while getting the config directory:\n    {e}"))?
+                    .join("anyrun")
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+
+            let mut ok = true;
+            for plugin in plugins() {
+                let file_name = plugin.file_name();
+                println!("// {file_name}");
+                if (plugin.check)(&config_dir, plugin.name, &file_name)? {
+                    ok = false;
+                }
+                println!();
+            }
+            Ok(ok)
+        }
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("(config-check) {e}");
+            process::exit(2);
+        }
+    }
+}
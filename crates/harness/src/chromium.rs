@@ -0,0 +1,210 @@
+use rusqlite::{params, Connection};
+use serde_json::json;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::write;
+
+struct Keyword {
+    short_name: String,
+    keyword: String,
+    url: String,
+    suggest_url: String,
+    favicon_url: String,
+    // The bitmap of the favicon, in the Favicons database:
+    favicon: Vec<u8>,
+}
+
+/// A profile of Chromium (or a browser built on it) with just the parts of Web Data and
+/// Preferences which the plugins read. Nothing is written until `write()` is called.
+pub struct ChromiumProfile {
+    dir: PathBuf,
+    keywords: Vec<Keyword>,
+    default_engine: Option<String>,
+}
+
+impl ChromiumProfile {
+    pub(crate) fn new(config_home: &Path, browser_dir: &str, profile: &str) -> Self {
+        ChromiumProfile {
+            dir: config_home.join(browser_dir).join(profile),
+            keywords: Vec::new(),
+            default_engine: None,
+        }
+    }
+
+    /// A search engine from the settings, e.g.
+    /// `("DuckDuckGo", "d", "https://duckduckgo.com/?q={searchTerms}")`.
+    pub fn engine(mut self, short_name: &str, keyword: &str, url: &str) -> Self {
+        self.keywords.push(Keyword {
+            short_name: short_name.to_string(),
+            keyword: keyword.to_string(),
+            url: url.to_string(),
+            suggest_url: String::new(),
+            favicon_url: String::new(),
+            favicon: Vec::new(),
+        });
+        self
+    }
+
+    /// Gives the engine which was added last a suggestions URL.
+    pub fn suggestions(mut self, suggest_url: &str) -> Self {
+        if let Some(keyword) = self.keywords.last_mut() {
+            keyword.suggest_url = suggest_url.to_string();
+        }
+        self
+    }
+
+    /// Gives the engine which was added last a favicon with the given URL and bitmap.
+    pub fn favicon(mut self, favicon_url: &str, image: &[u8]) -> Self {
+        if let Some(keyword) = self.keywords.last_mut() {
+            keyword.favicon_url = favicon_url.to_string();
+            keyword.favicon = image.to_vec();
+        }
+        self
+    }
+
+    /// Makes the engine with the given name the default one.
+    pub fn default_engine(mut self, short_name: &str) -> Self {
+        self.default_engine = Some(short_name.to_string());
+        self
+    }
+
+    /// Writes Web Data, Favicons and Preferences.
+    pub fn write(self) -> PathBuf {
+        fs::create_dir_all(&self.dir)
+            .unwrap_or_else(|e| panic!("Failed while creating {:?}:\n    {e}", self.dir));
+        self.write_web_data();
+        self.write_favicons();
+
+        // Chromium keeps the default engine by the GUID of its row:
+        let mut preferences = json!({});
+        if let Some(name) = &self.default_engine {
+            let i = self
+                .keywords
+                .iter()
+                .position(|keyword| &keyword.short_name == name)
+                .unwrap_or_else(|| panic!("There is no engine called {name:?}"));
+            preferences["default_search_provider"]["guid"] = json!(guid(i));
+        }
+        write(&self.dir.join("Preferences"), preferences.to_string());
+
+        self.dir
+    }
+
+    fn write_web_data(&self) {
+        // Writing a profile again replaces it:
+        let path = self.dir.join("Web Data");
+        if path.exists() {
+            fs::remove_file(&path)
+                .unwrap_or_else(|e| panic!("Failed while removing {path:?}:\n    {e}"));
+        }
+        let conn = Connection::open(&path)
+            .unwrap_or_else(|e| panic!("Failed while creating {path:?}:\n    {e}"));
+        conn.execute_batch(
+            "CREATE TABLE meta (
+                key LONGVARCHAR NOT NULL UNIQUE PRIMARY KEY,
+                value LONGVARCHAR
+            );
+            CREATE TABLE keywords (
+                id INTEGER PRIMARY KEY,
+                short_name VARCHAR NOT NULL,
+                keyword VARCHAR NOT NULL,
+                favicon_url VARCHAR NOT NULL,
+                url VARCHAR NOT NULL,
+                safe_for_autoreplace INTEGER,
+                input_encodings VARCHAR,
+                suggest_url VARCHAR,
+                prepopulate_id INTEGER DEFAULT 0,
+                sync_guid VARCHAR
+            );",
+        )
+        .expect("Failed while creating the Web Data tables");
+
+        for (i, keyword) in self.keywords.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO keywords
+                    (id, short_name, keyword, favicon_url, url, safe_for_autoreplace,
+                    input_encodings, suggest_url, sync_guid)
+                VALUES (?1, ?2, ?3, ?4, ?5, 0, 'UTF-8', ?6, ?7)",
+                params![
+                    i as i64 + 1,
+                    keyword.short_name,
+                    keyword.keyword,
+                    keyword.favicon_url,
+                    keyword.url,
+                    keyword.suggest_url,
+                    guid(i),
+                ],
+            )
+            .expect("Failed while inserting a keyword");
+        }
+    }
+
+    fn write_favicons(&self) {
+        let path = self.dir.join("Favicons");
+        if path.exists() {
+            fs::remove_file(&path)
+                .unwrap_or_else(|e| panic!("Failed while removing {path:?}:\n    {e}"));
+        }
+        let conn = Connection::open(&path)
+            .unwrap_or_else(|e| panic!("Failed while creating {path:?}:\n    {e}"));
+        conn.execute_batch(
+            "CREATE TABLE favicons (
+                id INTEGER PRIMARY KEY,
+                url LONGVARCHAR NOT NULL,
+                icon_type INTEGER DEFAULT 1
+            );
+            CREATE TABLE favicon_bitmaps (
+                id INTEGER PRIMARY KEY,
+                icon_id INTEGER NOT NULL,
+                last_updated INTEGER DEFAULT 0,
+                image_data BLOB,
+                width INTEGER DEFAULT 0,
+                height INTEGER DEFAULT 0
+            );",
+        )
+        .expect("Failed while creating the Favicons tables");
+
+        for (i, keyword) in self.keywords.iter().enumerate() {
+            if keyword.favicon_url.is_empty() {
+                continue;
+            }
+            conn.execute(
+                "INSERT INTO favicons (id, url) VALUES (?1, ?2)",
+                params![i as i64 + 1, keyword.favicon_url],
+            )
+            .expect("Failed while inserting a favicon");
+            // A bigger bitmap without any data is there too, like a size which isn't
+            // downloaded yet:
+            conn.execute(
+                "INSERT INTO favicon_bitmaps (icon_id, image_data, width, height)
+                VALUES (?1, ?2, 16, 16), (?1, X'', 32, 32)",
+                params![i as i64 + 1, keyword.favicon],
+            )
+            .expect("Failed while inserting a favicon bitmap");
+        }
+    }
+}
+
+// Chromium gives every engine a GUID:
+fn guid(i: usize) -> String {
+    format!("00000000-0000-4000-8000-{i:012}")
+}
+
+/// Holds an exclusive lock on an SQLite database until it is dropped, the way a running
+/// Chromium does with its Web Data.
+pub struct DatabaseLock {
+    _conn: Connection,
+}
+
+impl DatabaseLock {
+    pub fn new(path: &Path) -> Self {
+        let conn = Connection::open(path)
+            .unwrap_or_else(|e| panic!("Failed while opening {path:?}:\n    {e}"));
+        conn.execute_batch("PRAGMA locking_mode = EXCLUSIVE; BEGIN EXCLUSIVE;")
+            .unwrap_or_else(|e| panic!("Failed while locking {path:?}:\n    {e}"));
+        DatabaseLock { _conn: conn }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+};
+use zbus::{blocking::Connection, zvariant::OwnedValue};
+
+/// A `dbus-daemon` of its own at the address in `DBUS_SESSION_BUS_ADDRESS`, so the plugins
+/// can talk to fake applications instead of the real session. It is stopped when dropped.
+pub struct SessionBus {
+    daemon: Child,
+}
+
+impl SessionBus {
+    pub(crate) fn new(socket: &Path) -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .arg("--session")
+            .arg("--nofork")
+            .arg("--print-address")
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("Failed while starting dbus-daemon:\n    {e}"));
+
+        // The address is printed once the daemon listens, so nobody connects too early:
+        let stdout = daemon.stdout.take().expect("The stdout is piped");
+        let mut address = String::new();
+        BufReader::new(stdout)
+            .read_line(&mut address)
+            .unwrap_or_else(|e| {
+                panic!("Failed while reading the address of dbus-daemon:\n    {e}")
+            });
+        assert!(!address.is_empty(), "dbus-daemon exited before listening");
+
+        SessionBus { daemon }
+    }
+
+    /// Puts an application on the bus under its desktop file ID, like a `DBusActivatable`
+    /// application which is already running.
+    pub fn application(&self, appid: &str) -> FakeApplication {
+        FakeApplication::new(appid)
+    }
+}
+
+impl Drop for SessionBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// A call to `org.freedesktop.Application`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplicationCall {
+    Activate,
+    ActivateAction(String),
+    Open(Vec<String>),
+}
+
+/// An application which implements `org.freedesktop.Application` and remembers how it was
+/// called. It leaves the bus when dropped.
+pub struct FakeApplication {
+    _conn: Connection,
+    calls: Arc<Mutex<Vec<ApplicationCall>>>,
+}
+
+struct Application {
+    calls: Arc<Mutex<Vec<ApplicationCall>>>,
+}
+
+impl Application {
+    fn record(&self, call: ApplicationCall) {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(call);
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Application")]
+impl Application {
+    fn activate(&self, _platform_data: HashMap<String, OwnedValue>) {
+        self.record(ApplicationCall::Activate);
+    }
+
+    fn activate_action(
+        &self,
+        action_name: String,
+        _parameter: Vec<OwnedValue>,
+        _platform_data: HashMap<String, OwnedValue>,
+    ) {
+        self.record(ApplicationCall::ActivateAction(action_name));
+    }
+
+    fn open(&self, uris: Vec<String>, _platform_data: HashMap<String, OwnedValue>) {
+        self.record(ApplicationCall::Open(uris));
+    }
+}
+
+impl FakeApplication {
+    fn new(appid: &str) -> Self {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        // The object path is derived from the bus name, as in the spec:
+        let path = format!("/{}", appid.replace('.', "/").replace('-', "_"));
+        let conn = zbus::blocking::connection::Builder::session()
+            .and_then(|builder| builder.name(appid.to_string()))
+            .and_then(|builder| {
+                builder.serve_at(
+                    path,
+                    Application {
+                        calls: calls.clone(),
+                    },
+                )
+            })
+            .and_then(|builder| builder.build())
+            .unwrap_or_else(|e| {
+                panic!("Failed while putting {appid} on the session bus:\n    {e}")
+            });
+
+        FakeApplication { _conn: conn, calls }
+    }
+
+    /// Every call so far, oldest first.
+    pub fn calls(&self) -> Vec<ApplicationCall> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -0,0 +1,280 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::write;
+
+// The same shape as the Bookmarks plugin's cache, see `FirefoxProfile::write()`:
+#[derive(Serialize)]
+struct Bookmark {
+    title: String,
+    url: String,
+    keyword: String,
+}
+
+/// A Firefox profile with just the parts of places.sqlite and search.json.mozlz4 which the
+/// plugins read. Nothing is written until `write()` is called.
+pub struct FirefoxProfile {
+    home: PathBuf,
+    dir: PathBuf,
+    bookmarks: Vec<Bookmark>,
+    engines: Vec<Value>,
+    default_engine: Option<String>,
+    search_version: u64,
+    // Replaces the generated search.json:
+    search_json: Option<String>,
+}
+
+impl FirefoxProfile {
+    pub(crate) fn new(home: &Path, dir_name: &str) -> Self {
+        FirefoxProfile {
+            home: home.to_path_buf(),
+            dir: home.join(".mozilla/firefox").join(dir_name),
+            bookmarks: Vec::new(),
+            engines: Vec::new(),
+            default_engine: None,
+            search_version: 6,
+            search_json: None,
+        }
+    }
+
+    pub fn bookmark(mut self, title: &str, url: &str) -> Self {
+        self.bookmarks.push(Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            keyword: String::new(),
+        });
+        self
+    }
+
+    /// A bookmark with a keyword, which Firefox (and the Bookmarks plugin) opens directly.
+    pub fn keyword_bookmark(mut self, title: &str, url: &str, keyword: &str) -> Self {
+        self.bookmarks.push(Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            keyword: keyword.to_string(),
+        });
+        self
+    }
+
+    /// A search engine which the user added, e.g.
+    /// `("DuckDuckGo", Some("@d"), "https://duckduckgo.com/", &[("q", "{searchTerms}")])`.
+    pub fn engine(
+        mut self,
+        name: &str,
+        alias: Option<&str>,
+        template: &str,
+        params: &[(&str, &str)],
+    ) -> Self {
+        let mut engine = json!({
+            // Firefox gives the engines of the user a UUID:
+            "id": format!("00000000-0000-0000-0000-{:012}", self.engines.len()),
+            "_name": name,
+            "_isAppProvided": false,
+            "_urls": [{
+                "template": template,
+                "params": params
+                    .iter()
+                    .map(|(name, value)| json!({ "name": name, "value": value }))
+                    .collect::<Vec<_>>(),
+            }],
+        });
+        if let Some(alias) = alias {
+            engine["_definedAliases"] = json!([alias]);
+        }
+        self.engines.push(engine);
+        self
+    }
+
+    /// Sets the charset of the engine which was added last, e.g. "windows-1252".
+    pub fn query_charset(mut self, charset: &str) -> Self {
+        if let Some(engine) = self.engines.last_mut() {
+            engine["_queryCharset"] = json!(charset);
+        }
+        self
+    }
+
+    /// Gives the engine which was added last a suggestions URL, e.g. the one of a
+    /// `SuggestionServer`.
+    pub fn suggestions(mut self, template: &str) -> Self {
+        if let Some(urls) = self
+            .engines
+            .last_mut()
+            .and_then(|engine| engine["_urls"].as_array_mut())
+        {
+            urls.push(json!({
+                "template": template,
+                "type": "application/x-suggestions+json",
+                "params": [],
+            }));
+        }
+        self
+    }
+
+    /// A search engine which ships with Firefox, e.g. `("ddg", "DuckDuckGo")`. Those only
+    /// have an id and a name in search.json.
+    pub fn app_provided_engine(mut self, id: &str, name: &str) -> Self {
+        self.engines.push(json!({
+            "id": id,
+            "_name": name,
+            "_isAppProvided": true,
+        }));
+        self
+    }
+
+    /// Gives the engine which was added last an icon, which is usually a data URI.
+    pub fn icon(mut self, icon_url: &str) -> Self {
+        if let Some(engine) = self.engines.last_mut() {
+            engine["_iconURL"] = json!(icon_url);
+        }
+        self
+    }
+
+    /// Sets the keyword of the engine which was added last, the way the settings do.
+    pub fn keyword(mut self, alias: &str) -> Self {
+        if let Some(engine) = self.engines.last_mut() {
+            engine["_metaData"]["alias"] = json!(alias);
+        }
+        self
+    }
+
+    /// Hides the engine which was added last, like removing it in the settings does.
+    pub fn hidden(mut self) -> Self {
+        if let Some(engine) = self.engines.last_mut() {
+            engine["_metaData"]["hidden"] = json!(true);
+        }
+        self
+    }
+
+    /// Makes the engine with the given name the default one.
+    pub fn default_engine(mut self, name: &str) -> Self {
+        self.default_engine = Some(name.to_string());
+        self
+    }
+
+    /// The version field of search.json, which changes how some fields look.
+    pub fn search_version(mut self, version: u64) -> Self {
+        self.search_version = version;
+        self
+    }
+
+    /// Writes this instead of the search.json which the engines above would make, e.g. a
+    /// fixture of an old version.
+    pub fn search_json(mut self, json: &str) -> Self {
+        self.search_json = Some(json.to_string());
+        self
+    }
+
+    /// Writes places.sqlite, search.json.mozlz4 and the bookmarks cache.
+    pub fn write(self) -> PathBuf {
+        fs::create_dir_all(&self.dir)
+            .unwrap_or_else(|e| panic!("Failed while creating {:?}:\n    {e}", self.dir));
+        self.write_places();
+        self.write_search();
+
+        // The Bookmarks plugin reads its cache instead of places.sqlite while a Firefox is
+        // running anywhere on the machine, so it is written as well. Otherwise the tests
+        // would fail on any desktop with a browser open:
+        let cache = ron::to_string(&self.bookmarks).expect("Failed while serializing bookmarks");
+        write(
+            &self
+                .home
+                .join(".cache/anyrun-plugins/firefox-bookmarks.ron"),
+            cache,
+        );
+
+        self.dir
+    }
+
+    fn write_places(&self) {
+        // Writing a profile again replaces it:
+        let path = self.dir.join("places.sqlite");
+        if path.exists() {
+            fs::remove_file(&path)
+                .unwrap_or_else(|e| panic!("Failed while removing {path:?}:\n    {e}"));
+        }
+        let conn = Connection::open(&path)
+            .unwrap_or_else(|e| panic!("Failed while creating {path:?}:\n    {e}"));
+        conn.execute_batch(
+            "CREATE TABLE moz_places (
+                id INTEGER PRIMARY KEY,
+                url LONGVARCHAR,
+                title LONGVARCHAR
+            );
+            CREATE TABLE moz_bookmarks (
+                id INTEGER PRIMARY KEY,
+                type INTEGER,
+                fk INTEGER DEFAULT NULL,
+                parent INTEGER,
+                title LONGVARCHAR
+            );
+            CREATE TABLE moz_keywords (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                keyword TEXT UNIQUE,
+                place_id INTEGER
+            );
+            -- The root and the menu folder, like in a real profile (type 2 is a folder):
+            INSERT INTO moz_bookmarks (id, type, parent, title) VALUES (1, 2, 0, '');
+            INSERT INTO moz_bookmarks (id, type, parent, title) VALUES (2, 2, 1, 'menu');",
+        )
+        .expect("Failed while creating the places tables");
+
+        for (i, bookmark) in self.bookmarks.iter().enumerate() {
+            let place = i as i64 + 1;
+            conn.execute(
+                "INSERT INTO moz_places (id, url, title) VALUES (?1, ?2, ?3)",
+                params![place, bookmark.url, bookmark.title],
+            )
+            .expect("Failed while inserting a place");
+            conn.execute(
+                "INSERT INTO moz_bookmarks (type, fk, parent, title) VALUES (1, ?1, 2, ?2)",
+                params![place, bookmark.title],
+            )
+            .expect("Failed while inserting a bookmark");
+            if !bookmark.keyword.is_empty() {
+                conn.execute(
+                    "INSERT INTO moz_keywords (keyword, place_id) VALUES (?1, ?2)",
+                    params![bookmark.keyword, place],
+                )
+                .expect("Failed while inserting a keyword");
+            }
+        }
+    }
+
+    fn write_search(&self) {
+        // Version 7 keeps the default engine by id instead of by name. Firefox would also
+        // check the hash, but the plugins don't:
+        let mut meta_data = json!({});
+        if let Some(name) = &self.default_engine {
+            let engine = self
+                .engines
+                .iter()
+                .find(|engine| engine["_name"] == name.as_str())
+                .unwrap_or_else(|| panic!("There is no engine called {name:?}"));
+            match self.search_version {
+                ..=6 => meta_data["current"] = json!(name),
+                _ => {
+                    meta_data["defaultEngineId"] = engine["id"].clone();
+                    meta_data["defaultEngineIdHash"] = json!("");
+                }
+            }
+        }
+
+        let search = match &self.search_json {
+            Some(json) => json.clone(),
+            None => json!({
+                "version": self.search_version,
+                "engines": self.engines,
+                "metaData": meta_data,
+            })
+            .to_string(),
+        };
+        let compressed =
+            mozlz4::compress(search.into_bytes()).expect("Failed while compressing search.json");
+        write(&self.dir.join("search.json.mozlz4"), compressed);
+    }
+}
@@ -0,0 +1,195 @@
+//! Drives the plugins the way anyrun does, but offline. Every `Harness` gets its own fake
+//! `HOME` and XDG directories, which the tests fill with config files, desktop files and
+//! Firefox profiles, and the programs which the plugins launch are recorded instead of spawned.
+
+use common::launch::{self, Launch};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+use tempfile::TempDir;
+
+mod chromium;
+mod dbus;
+mod firefox;
+mod plugin;
+mod suggestions;
+
+pub use anyrun_plugin::{HandleResult, Match, PluginInfo};
+pub use chromium::{ChromiumProfile, DatabaseLock};
+pub use dbus::{ApplicationCall, FakeApplication, SessionBus};
+pub use firefox::FirefoxProfile;
+pub use plugin::Plugin;
+pub use suggestions::SuggestionServer;
+
+// The env variables are shared by the whole process, and so are the statics of a loaded
+// plugin, which is why only one harness can exist at a time:
+static LOCK: Mutex<()> = Mutex::new(());
+
+// Where the fake directories live inside the temporary directory:
+const DIRS: &[(&str, &str)] = &[
+    ("HOME", "home"),
+    ("XDG_CONFIG_HOME", "home/.config"),
+    ("XDG_DATA_HOME", "home/.local/share"),
+    ("XDG_STATE_HOME", "home/.local/state"),
+    ("XDG_CACHE_HOME", "home/.cache"),
+    ("XDG_CONFIG_DIRS", "etc/xdg"),
+    ("XDG_DATA_DIRS", "usr/share"),
+];
+
+// Anything which could reach the real session. Without a display or a session bus of the
+// test's own, the activation tokens and D-Bus activation fail right away:
+const SESSION_VARS: &[&str] = &[
+    "WAYLAND_DISPLAY",
+    "DISPLAY",
+    "XDG_CURRENT_DESKTOP",
+    "XDG_ACTIVATION_TOKEN",
+    "DESKTOP_STARTUP_ID",
+];
+
+/// A fake environment for the plugins. The env variables point into it until it is dropped.
+pub struct Harness {
+    root: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        // A test which panicked while holding the lock doesn't matter, because every
+        // harness sets the environment up from scratch:
+        let lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let root = tempfile::tempdir().expect("Failed while creating the temporary directory");
+
+        for (var, dir) in DIRS {
+            let path = root.path().join(dir);
+            fs::create_dir_all(&path)
+                .unwrap_or_else(|e| panic!("Failed while creating {path:?}:\n    {e}"));
+            env::set_var(var, path);
+        }
+        for var in SESSION_VARS {
+            env::remove_var(var);
+        }
+        env::set_var(
+            "DBUS_SESSION_BUS_ADDRESS",
+            format!("unix:path={}", root.path().join("bus").display()),
+        );
+        env::set_var(launch::RECORD_VAR, root.path().join("launches.ron"));
+        env::set_var(common::INIT_MARKER_VAR, root.path().join("init"));
+        // The locale ends up in some URLs, so it has to be the same everywhere:
+        env::remove_var("LC_ALL");
+        env::remove_var("LC_MESSAGES");
+        env::set_var("LANG", "en_US.UTF-8");
+
+        let harness = Harness { root, _lock: lock };
+        fs::create_dir_all(harness.config_dir()).expect("Failed while creating the config dir");
+        harness
+    }
+
+    /// The fake home directory.
+    pub fn home(&self) -> PathBuf {
+        self.root.path().join("home")
+    }
+
+    /// The config directory which is passed to the plugins, like `~/.config/anyrun`.
+    pub fn config_dir(&self) -> PathBuf {
+        self.home().join(".config/anyrun")
+    }
+
+    /// Writes a config file, e.g. `("Common.ron", "CommonConfig(...)")`, to the config directory.
+    pub fn config(&self, file_name: &str, contents: &str) -> &Self {
+        write(&self.config_dir().join(file_name), contents);
+        self
+    }
+
+    /// Writes a system-wide config file to `$XDG_CONFIG_DIRS/anyrun`.
+    pub fn system_config(&self, file_name: &str, contents: &str) -> &Self {
+        write(
+            &self.root.path().join("etc/xdg/anyrun").join(file_name),
+            contents,
+        );
+        self
+    }
+
+    /// Writes a desktop file with the given desktop file ID, e.g. "firefox.desktop", to the
+    /// applications directory in `$XDG_DATA_DIRS`.
+    pub fn desktop_file(&self, id: &str, contents: &str) -> &Self {
+        write(
+            &self.root.path().join("usr/share/applications").join(id),
+            contents,
+        );
+        self
+    }
+
+    /// Makes the desktop file ID the handler of https links in mimeapps.list.
+    pub fn default_browser(&self, id: &str) -> &Self {
+        write(
+            &self.home().join(".config/mimeapps.list"),
+            format!("[Default Applications]\nx-scheme-handler/https={id}\n"),
+        );
+        self
+    }
+
+    /// Starts a Firefox profile in `~/.mozilla/firefox/<dir_name>`. The plugins look for the
+    /// directory whose name contains the profile name, so "abcd1234.default" is found as "default".
+    pub fn firefox_profile(&self, dir_name: &str) -> FirefoxProfile {
+        FirefoxProfile::new(&self.home(), dir_name)
+    }
+
+    /// Starts a profile of Chromium or a browser built on it, e.g. `("chromium", "Default")`
+    /// for `~/.config/chromium/Default`.
+    pub fn chromium_profile(&self, browser_dir: &str, profile: &str) -> ChromiumProfile {
+        ChromiumProfile::new(&self.home().join(".config"), browser_dir, profile)
+    }
+
+    /// Starts a session bus at the address the plugins use. Until then, there is none.
+    pub fn session_bus(&self) -> SessionBus {
+        SessionBus::new(&self.root.path().join("bus"))
+    }
+
+    /// Builds the plugin with the given package name, loads it and calls its init with the
+    /// config directory.
+    pub fn load(&self, package: &str) -> Plugin<'_> {
+        Plugin::load(package, &self.config_dir(), &self.root.path().join("init"))
+    }
+
+    /// Everything the plugins have launched so far, oldest first.
+    pub fn launches(&self) -> Vec<Launch> {
+        let path = self.root.path().join("launches.ron");
+        let record = match fs::read_to_string(&path) {
+            Ok(record) => record,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => panic!("Failed while reading {path:?}:\n    {e}"),
+        };
+        record
+            .lines()
+            .map(|line| {
+                ron::from_str(line).unwrap_or_else(|e| {
+                    panic!("Failed while parsing the launch {line:?}:\n    {e}")
+                })
+            })
+            .collect()
+    }
+
+    /// The argv of every launch so far, which is all most tests care about.
+    pub fn launched(&self) -> Vec<Vec<String>> {
+        self.launches()
+            .into_iter()
+            .map(|launch| launch.argv)
+            .collect()
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn write(path: &Path, contents: impl AsRef<[u8]>) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .unwrap_or_else(|e| panic!("Failed while creating {parent:?}:\n    {e}"));
+    }
+    fs::write(path, contents).unwrap_or_else(|e| panic!("Failed while writing {path:?}:\n    {e}"));
+}
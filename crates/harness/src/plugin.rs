@@ -0,0 +1,148 @@
+use abi_stable::{library::lib_header_from_path, std_types::RString};
+use anyrun_plugin::{
+    anyrun_interface::{PluginRef, PollResult},
+    HandleResult, Match, PluginInfo,
+};
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// If this env variable holds a directory, the plugins are loaded from there (as
+/// `lib<package>.so`) instead of being built first. They have to be built with the `harness`
+/// feature of common, e.g. `cargo build --lib --features common/harness`.
+pub const LIBS_VAR: &str = "ANYRUN_PLUGINS_HARNESS_LIBS";
+
+// How long a query may take before the test fails:
+const TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// The packages which were already built by this process:
+static BUILT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A loaded plugin. It borrows the harness, so the fake environment outlives it.
+pub struct Plugin<'a> {
+    plugin: PluginRef,
+    _harness: PhantomData<&'a ()>,
+}
+
+impl Plugin<'_> {
+    pub(crate) fn load(package: &str, config_dir: &Path, init_marker: &Path) -> Self {
+        let path = library(package);
+        // This is exactly how anyrun loads plugins:
+        let plugin = lib_header_from_path(&path)
+            .and_then(|header| header.init_root_module::<PluginRef>())
+            .unwrap_or_else(|e| panic!("Failed while loading {path:?}:\n    {e}"));
+
+        // Init runs in a thread of its own, which holds the plugin's data until it is done.
+        // Queries wait for it, but only once that thread has started, which the plugin says
+        // by creating the marker:
+        match fs::remove_file(init_marker) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                panic!("Failed while removing {init_marker:?}:\n    {e}")
+            }
+            _ => (),
+        }
+        plugin.init()(RString::from(config_dir.to_string_lossy().as_ref()));
+        let start = Instant::now();
+        while !init_marker.exists() {
+            if start.elapsed() > TIMEOUT {
+                panic!(
+                    "The init of {package} didn't start within {TIMEOUT:?}! Plugins have to load \
+                    a config file in their init and be built with --features common/harness"
+                );
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        Plugin {
+            plugin,
+            _harness: PhantomData,
+        }
+    }
+
+    pub fn info(&self) -> PluginInfo {
+        self.plugin.info()()
+    }
+
+    /// Queries the plugin and waits for its matches, like anyrun does for every keystroke.
+    pub fn matches(&self, input: &str) -> Vec<Match> {
+        let id = self.plugin.get_matches()(RString::from(input));
+        let start = Instant::now();
+        loop {
+            match self.plugin.poll_matches()(id) {
+                PollResult::Ready(matches) => return matches.into_iter().collect(),
+                PollResult::Pending => (),
+                PollResult::Cancelled => panic!("The query {input:?} was cancelled!"),
+            }
+            if start.elapsed() > TIMEOUT {
+                panic!("The query {input:?} took longer than {TIMEOUT:?}!");
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Selects a match, like pressing enter on it in anyrun.
+    pub fn select(&self, selection: &Match) -> HandleResult {
+        self.plugin.handle_selection()(selection.clone())
+    }
+
+    /// The titles of the matches for `input`, which is all most tests care about.
+    pub fn titles(&self, input: &str) -> Vec<String> {
+        self.matches(input)
+            .into_iter()
+            .map(|m| m.title.to_string())
+            .collect()
+    }
+}
+
+// Returns the path of the plugin's library, building it first if necessary. The plugins are
+// built into a target directory of their own, because cargo keeps the one of the test run
+// locked while the tests are running:
+fn library(package: &str) -> PathBuf {
+    let file_name = format!("lib{package}.so");
+    if let Some(dir) = env::var_os(LIBS_VAR) {
+        return PathBuf::from(dir).join(file_name);
+    }
+
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let target_dir = workspace
+        .join(env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| OsString::from("target")))
+        .join("harness");
+
+    let mut built = BUILT.lock().unwrap_or_else(|e| e.into_inner());
+    if !built.iter().any(|p| p == package) {
+        let output = Command::new(env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")))
+            .current_dir(&workspace)
+            .args([
+                "build",
+                "--quiet",
+                "--lib",
+                "--package",
+                package,
+                // The launches are only recorded (and init only creates the marker) with this:
+                "--features",
+                "common/harness",
+                "--target-dir",
+            ])
+            .arg(&target_dir)
+            .output()
+            .unwrap_or_else(|e| panic!("Failed while running cargo:\n    {e}"));
+        if !output.status.success() {
+            panic!(
+                "Failed while building {package}:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        built.push(package.to_string());
+    }
+
+    target_dir.join("debug").join(file_name)
+}
@@ -0,0 +1,67 @@
+use serde_json::json;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+use tiny_http::{Header, Response, Server};
+
+/// A search engine's suggestion endpoint on localhost, which answers every request with the
+/// same suggestions and remembers what it was asked.
+pub struct SuggestionServer {
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl SuggestionServer {
+    pub fn new(suggestions: &[&str]) -> Self {
+        let server =
+            Arc::new(Server::http("127.0.0.1:0").unwrap_or_else(|e| {
+                panic!("Failed while starting the suggestion server:\n    {e}")
+            }));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let suggestions: Vec<String> = suggestions.iter().map(|s| s.to_string()).collect();
+
+        let (thread_server, thread_requests) = (server.clone(), requests.clone());
+        thread::spawn(move || {
+            // The loop ends once the server is unblocked when it is dropped:
+            for request in thread_server.incoming_requests() {
+                let url = request.url().to_string();
+                // The answer starts with the search terms, which don't matter to the plugin:
+                let body = json!(["", suggestions]).to_string();
+                thread_requests
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(url);
+                let header = Header::from_bytes("Content-Type", "application/x-suggestions+json")
+                    .expect("Failed while creating the header");
+                let _ = request.respond(Response::from_string(body).with_header(header));
+            }
+        });
+
+        SuggestionServer { server, requests }
+    }
+
+    /// The URL template which Firefox would have, e.g. `http://127.0.0.1:1234/?q={searchTerms}`.
+    pub fn template(&self) -> String {
+        let addr = self
+            .server
+            .server_addr()
+            .to_ip()
+            .expect("The server listens on TCP");
+        format!("http://{addr}/?q={{searchTerms}}")
+    }
+
+    /// The path and query of every request so far, oldest first.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Drop for SuggestionServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
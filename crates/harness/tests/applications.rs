@@ -0,0 +1,229 @@
+use harness::{ApplicationCall, HandleResult, Harness};
+
+fn harness() -> Harness {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "firefox.desktop",
+            "[Desktop Entry]
+Type=Application
+Name=Firefox
+Comment=Browse the Web
+Icon=firefox
+Exec=firefox %u
+Actions=new-window;new-private-window;
+
+[Desktop Action new-window]
+Name=New Window
+Exec=firefox --new-window %u
+
+[Desktop Action new-private-window]
+Name=New Private Window
+Exec=firefox --private-window %u
+",
+        )
+        .desktop_file(
+            "org.gnome.Calculator.desktop",
+            "[Desktop Entry]
+Type=Application
+Name=Calculator
+Comment=Perform arithmetic, scientific or financial calculations
+Icon=org.gnome.Calculator
+Exec=gnome-calculator
+Path=/tmp
+",
+        )
+        .desktop_file(
+            "htop.desktop",
+            "[Desktop Entry]
+Type=Application
+Name=Htop
+Comment=Show System Processes
+Icon=htop
+Exec=htop
+",
+        );
+    harness
+}
+
+#[test]
+fn entries_are_matched_by_name() {
+    let harness = harness();
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("calc");
+    assert_eq!(matches[0].title.as_str(), "Calculator");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Perform arithmetic, scientific or financial calculations"
+    );
+    assert_eq!(
+        matches[0].icon.clone().unwrap().as_str(),
+        "org.gnome.Calculator"
+    );
+}
+
+#[test]
+fn blank_input_shows_nothing_by_default() {
+    let harness = harness();
+    let plugin = harness.load("applications");
+
+    assert!(plugin.matches("").is_empty());
+}
+
+#[test]
+fn blank_input_shows_the_curated_entries() {
+    let harness = harness();
+    harness.config(
+        "Applications.ron",
+        r#"Config(bib: Some(Currated(["org.gnome.Calculator", "htop"])))"#,
+    );
+    let plugin = harness.load("applications");
+
+    // The entries are in the order in which the desktop files were found:
+    let mut titles = plugin.titles("");
+    titles.sort();
+    assert_eq!(titles, ["Calculator", "Htop"]);
+}
+
+#[test]
+fn selecting_an_entry_runs_its_exec() {
+    let harness = harness();
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("firefox");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+
+    let launches = harness.launches();
+    assert_eq!(launches.len(), 1);
+    assert_eq!(launches[0].argv, ["firefox"]);
+    assert_eq!(launches[0].app_id.as_deref(), Some("firefox"));
+}
+
+#[test]
+fn entries_are_run_in_their_path() {
+    let harness = harness();
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("calculator");
+    plugin.select(&matches[0]);
+
+    let launches = harness.launches();
+    assert_eq!(launches[0].argv, ["gnome-calculator"]);
+    assert_eq!(launches[0].cwd.as_deref(), Some("/tmp".as_ref()));
+}
+
+#[test]
+fn desktop_actions_are_only_shown_when_enabled() {
+    let harness = harness();
+    let plugin = harness.load("applications");
+    assert_eq!(plugin.titles("private"), Vec::<String>::new());
+
+    harness.config("Applications.ron", "Config(desktop_actions: Some(true))");
+    let plugin = harness.load("applications");
+    let matches = plugin.matches("private");
+    assert_eq!(matches[0].title.as_str(), "Firefox: New Private Window");
+
+    plugin.select(&matches[0]);
+    assert_eq!(harness.launched(), [["firefox", "--private-window"]]);
+}
+
+fn dbus_harness() -> Harness {
+    let harness = harness();
+    harness.desktop_file(
+        "org.example.Counter.desktop",
+        "[Desktop Entry]
+Type=Application
+Name=Counter
+Exec=counter %F
+DBusActivatable=true
+Actions=reset;
+
+[Desktop Action reset]
+Name=Reset
+Exec=counter --reset
+",
+    );
+    harness
+}
+
+#[test]
+fn dbus_activatable_entries_are_activated_on_the_session_bus() {
+    let harness = dbus_harness();
+    let bus = harness.session_bus();
+    let app = bus.application("org.example.Counter");
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("counter");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+
+    assert_eq!(app.calls(), [ApplicationCall::Activate]);
+    assert!(harness.launches().is_empty());
+}
+
+#[test]
+fn files_after_the_separator_are_opened_on_the_session_bus() {
+    let harness = dbus_harness();
+    let bus = harness.session_bus();
+    let app = bus.application("org.example.Counter");
+    let plugin = harness.load("applications");
+
+    // Only the entries which can open files are matched, so the Calculator isn't:
+    let matches = plugin.matches("co -- ~/notes.md https://example.org/a%20b");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "Counter");
+    plugin.select(&matches[0]);
+
+    let notes = harness.home().join("notes.md");
+    assert_eq!(
+        app.calls(),
+        [ApplicationCall::Open(vec![
+            format!("file://{}", notes.display()),
+            "https://example.org/a%20b".into(),
+        ])]
+    );
+    assert!(harness.launches().is_empty());
+}
+
+#[test]
+fn files_after_the_separator_are_passed_to_exec() {
+    let harness = dbus_harness();
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("counter -- a.md b.md");
+    plugin.select(&matches[0]);
+
+    assert_eq!(harness.launched(), [["counter", "a.md", "b.md"]]);
+}
+
+#[test]
+fn desktop_actions_of_dbus_activatable_entries_are_activated_by_name() {
+    let harness = dbus_harness();
+    harness.config("Applications.ron", "Config(desktop_actions: Some(true))");
+    let bus = harness.session_bus();
+    let app = bus.application("org.example.Counter");
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("reset");
+    assert_eq!(matches[0].title.as_str(), "Counter: Reset");
+    plugin.select(&matches[0]);
+
+    assert_eq!(
+        app.calls(),
+        [ApplicationCall::ActivateAction("reset".into())]
+    );
+    assert!(harness.launches().is_empty());
+}
+
+#[test]
+fn dbus_activatable_entries_fall_back_to_exec() {
+    let harness = dbus_harness();
+    // The bus is there, but the application isn't on it and can't be started:
+    let _bus = harness.session_bus();
+    let plugin = harness.load("applications");
+
+    let matches = plugin.matches("counter");
+    plugin.select(&matches[0]);
+
+    assert_eq!(harness.launched(), [["counter"]]);
+}
@@ -0,0 +1,150 @@
+use harness::{HandleResult, Harness};
+
+// Firefox is the default browser and its "default" profile has a few bookmarks:
+fn harness() -> Harness {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "firefox.desktop",
+            "[Desktop Entry]\nType=Application\nName=Firefox\nIcon=firefox\nExec=firefox %u\n",
+        )
+        .default_browser("firefox.desktop");
+    harness
+        .firefox_profile("abcd1234.default")
+        .bookmark(
+            "The Rust Programming Language",
+            "https://doc.rust-lang.org/book/",
+        )
+        .bookmark(
+            "Rust By Example",
+            "https://doc.rust-lang.org/rust-by-example/",
+        )
+        .keyword_bookmark("NixOS Search", "https://search.nixos.org/", "nix")
+        .write();
+    harness
+}
+
+#[test]
+fn bookmarks_are_matched_by_title() {
+    let harness = harness();
+    let plugin = harness.load("bookmarks");
+
+    let matches = plugin.matches("*example");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "Rust By Example");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "https://doc.rust-lang.org/rust-by-example/"
+    );
+}
+
+#[test]
+fn blank_input_shows_every_bookmark() {
+    let harness = harness();
+    let plugin = harness.load("bookmarks");
+
+    assert_eq!(
+        plugin.titles("*"),
+        [
+            "The Rust Programming Language",
+            "Rust By Example",
+            "NixOS Search"
+        ]
+    );
+}
+
+#[test]
+fn a_keyword_only_shows_its_bookmark() {
+    let harness = harness();
+    let plugin = harness.load("bookmarks");
+
+    assert_eq!(plugin.titles("nix"), ["NixOS Search"]);
+}
+
+#[test]
+fn selecting_a_bookmark_opens_it_in_a_new_window() {
+    let harness = harness();
+    let plugin = harness.load("bookmarks");
+
+    let matches = plugin.matches("*rust programming");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--new-window", "https://doc.rust-lang.org/book/"]]
+    );
+}
+
+#[test]
+fn a_trailing_modifier_opens_the_bookmark_in_a_new_tab() {
+    let harness = harness();
+    let plugin = harness.load("bookmarks");
+
+    // Keywords still work with a modifier after them:
+    let matches = plugin.matches("nix !tab");
+    assert_eq!(matches[0].title.as_str(), "NixOS Search");
+
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--new-tab", "https://search.nixos.org/"]]
+    );
+}
+
+#[test]
+fn the_profile_can_be_overridden() {
+    let harness = harness();
+    harness
+        .firefox_profile("efgh5678.work")
+        .bookmark("Issue Tracker", "https://issues.example.com/")
+        .write();
+    harness.config(
+        "Bookmarks.ron",
+        r#"Config(browser: Some((profile: Some("work"))))"#,
+    );
+    let plugin = harness.load("bookmarks");
+
+    let matches = plugin.matches("*issue");
+    assert_eq!(matches[0].title.as_str(), "Issue Tracker");
+
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "-P",
+            "work",
+            "--new-window",
+            "https://issues.example.com/"
+        ]]
+    );
+}
+
+#[test]
+fn a_failed_load_is_only_retried_for_input_with_the_prefix() {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "firefox.desktop",
+            "[Desktop Entry]\nType=Application\nName=Firefox\nIcon=firefox\nExec=firefox %u\n",
+        )
+        .default_browser("firefox.desktop");
+    let plugin = harness.load("bookmarks");
+
+    // Without a profile, loading fails, and the diagnostic is only shown after the prefix:
+    assert!(plugin.matches("nix").is_empty());
+    let matches = plugin.matches("*");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].title.as_str(),
+        "(Bookmarks) Failed to initialize"
+    );
+
+    harness
+        .firefox_profile("abcd1234.default")
+        .keyword_bookmark("NixOS Search", "https://search.nixos.org/", "nix")
+        .write();
+    assert!(plugin.matches("nix").is_empty());
+    assert_eq!(plugin.titles("*nixos"), ["NixOS Search"]);
+    // Once the bookmarks are loaded, keywords work without the prefix again:
+    assert_eq!(plugin.titles("nix"), ["NixOS Search"]);
+}
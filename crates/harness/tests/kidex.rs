@@ -0,0 +1,25 @@
+use harness::Harness;
+
+// There is no kidex daemon in the harness, so the index is always empty, but the config
+// files work the same as everywhere else:
+#[test]
+fn problems_in_the_config_file_are_shown() {
+    let harness = Harness::new();
+    harness.config("kidex.ron", "Config(max_entries: Some(\"3\"))");
+    let plugin = harness.load("kidex");
+
+    let matches = plugin.matches("notes");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "(Kidex) Problems in kidex.ron");
+}
+
+#[test]
+fn fixing_the_config_file_removes_the_problem() {
+    let harness = Harness::new();
+    harness.config("kidex.ron", "Config(max_entries: Some(\"3\"))");
+    let plugin = harness.load("kidex");
+    assert_eq!(plugin.matches("notes").len(), 1);
+
+    harness.config("kidex.ron", "Config(max_entries: Some(3))");
+    assert!(plugin.matches("notes").is_empty());
+}
@@ -0,0 +1,90 @@
+use harness::{HandleResult, Harness};
+
+#[test]
+fn blank_input_shows_every_option() {
+    let harness = Harness::new();
+    let plugin = harness.load("powermenu");
+
+    assert_eq!(
+        plugin.titles("!"),
+        ["Power off", "Reboot", "Suspend", "Lock"]
+    );
+}
+
+#[test]
+fn only_the_best_option_is_matched() {
+    let harness = Harness::new();
+    let plugin = harness.load("powermenu");
+
+    assert_eq!(plugin.titles("!reboot"), ["Reboot"]);
+    assert!(plugin.titles("reboot").is_empty());
+}
+
+#[test]
+fn selecting_an_option_runs_its_command() {
+    let harness = Harness::new();
+    let plugin = harness.load("powermenu");
+
+    let matches = plugin.matches("!lock");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+    assert_eq!(harness.launched(), [["loginctl", "lock-session"]]);
+}
+
+#[test]
+fn commands_get_the_prefix_args() {
+    let harness = Harness::new();
+    harness.config(
+        "Common.ron",
+        r#"CommonConfig(prefix_args: Some(["uwsm", "app", "--"]))"#,
+    );
+    let plugin = harness.load("powermenu");
+
+    let matches = plugin.matches("!suspend");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [["uwsm", "app", "--", "systemctl", "suspend"]]
+    );
+}
+
+#[test]
+fn the_prefix_can_be_changed() {
+    let harness = Harness::new();
+    harness.config("Powermenu.ron", r#"Config(prefix: Some(":power "))"#);
+    let plugin = harness.load("powermenu");
+
+    assert_eq!(plugin.titles(":power off"), ["Power off"]);
+    assert!(plugin.titles("!off").is_empty());
+}
+
+#[test]
+fn prefix_args_are_expanded() {
+    let harness = Harness::new();
+    harness.config(
+        "Common.ron",
+        r#"CommonConfig(prefix_args: Some(["~/bin/launch", "--tag=$$USER"]))"#,
+    );
+    let plugin = harness.load("powermenu");
+
+    let matches = plugin.matches("!suspend");
+    plugin.select(&matches[0]);
+    let launcher = harness.home().join("bin/launch");
+    assert_eq!(
+        harness.launched(),
+        [[
+            launcher.to_str().unwrap(),
+            "--tag=$USER",
+            "systemctl",
+            "suspend"
+        ]]
+    );
+}
+
+#[test]
+fn the_prefix_is_not_expanded() {
+    let harness = Harness::new();
+    harness.config("Powermenu.ron", r#"Config(prefix: Some("$"))"#);
+    let plugin = harness.load("powermenu");
+
+    assert_eq!(plugin.titles("$reboot"), ["Reboot"]);
+}
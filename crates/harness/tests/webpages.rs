@@ -0,0 +1,48 @@
+use harness::{HandleResult, Harness};
+
+// Firefox is the default browser:
+fn harness() -> Harness {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "firefox.desktop",
+            "[Desktop Entry]\nType=Application\nName=Firefox\nIcon=firefox\nExec=firefox %u\n",
+        )
+        .default_browser("firefox.desktop");
+    harness
+}
+
+#[test]
+fn selecting_a_page_opens_it_in_a_new_window() {
+    let harness = harness();
+    let plugin = harness.load("webpages");
+
+    let matches = plugin.matches("docs.rs/serde");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Open with Firefox"
+    );
+
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--new-window", "docs.rs/serde"]]
+    );
+}
+
+#[test]
+fn a_trailing_modifier_opens_the_page_in_a_private_window() {
+    let harness = harness();
+    let plugin = harness.load("webpages");
+
+    // The modifier isn't part of the page, so the input is still a valid one:
+    assert_eq!(plugin.titles("docs.rs/serde !p"), ["docs.rs/serde"]);
+
+    let matches = plugin.matches("docs.rs/serde !p");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--private-window", "docs.rs/serde"]]
+    );
+}
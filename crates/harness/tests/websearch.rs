@@ -0,0 +1,1060 @@
+use harness::{DatabaseLock, FirefoxProfile, HandleResult, Harness, Plugin, SuggestionServer};
+
+// Firefox is the default browser and its "default" profile has a few search engines:
+fn harness() -> Harness {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "firefox.desktop",
+            "[Desktop Entry]\nType=Application\nName=Firefox\nIcon=firefox\nExec=firefox %u\n",
+        )
+        .default_browser("firefox.desktop")
+        // The engines which ship with Firefox have real suggestion URLs, which the tests
+        // mustn't reach:
+        .config(
+            "Websearch.ron",
+            "Config(suggestions: Some(SuggestionsConfig(enabled: Some(false))))",
+        );
+    profile(&harness).write();
+    harness
+}
+
+// The engines of the "default" profile, which tests can add to before writing it again:
+fn profile(harness: &Harness) -> FirefoxProfile {
+    harness
+        .firefox_profile("abcd1234.default")
+        .app_provided_engine("google", "Google")
+        .engine(
+            "DuckDuckGo",
+            Some("@d"),
+            "https://duckduckgo.com/",
+            &[("q", "{searchTerms}")],
+        )
+        .engine(
+            "Nix Packages",
+            Some("@np"),
+            "https://search.nixos.org/packages",
+            &[("channel", "unstable"), ("query", "{searchTerms}")],
+        )
+        .engine(
+            "Wikipedia",
+            None,
+            "https://en.wikipedia.org/w/index.php",
+            &[("search", "{searchTerms}")],
+        )
+}
+
+#[test]
+fn an_alias_picks_its_engine() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@np ripgrep");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "ripgrep");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Nix Packages"
+    );
+}
+
+#[test]
+fn engines_without_an_alias_match_everything_else() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("ferris");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Wikipedia"
+    );
+}
+
+#[test]
+fn app_provided_engines_need_their_keyword() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let descriptions: Vec<String> = plugin
+        .matches("ferris")
+        .into_iter()
+        .filter_map(|m| m.description.into_option().map(|d| d.to_string()))
+        .collect();
+    assert!(!descriptions.iter().any(|d| d.contains("Google")));
+
+    let matches = plugin.matches("@google ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Google"
+    );
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://www.google.com/search?client=firefox-b-d&q=ferris"
+    );
+}
+
+#[test]
+fn keywords_from_the_settings_replace_the_app_provided_ones() {
+    let harness = harness();
+    profile(&harness)
+        .app_provided_engine("bing", "Bing")
+        .keyword("@b")
+        .write();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@b ferris");
+    assert_eq!(matches[0].title.as_str(), "ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Bing"
+    );
+}
+
+#[test]
+fn hidden_engines_are_skipped() {
+    let harness = harness();
+    profile(&harness)
+        .app_provided_engine("bing", "Bing")
+        .hidden()
+        .write();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@bing ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Wikipedia"
+    );
+}
+
+#[test]
+fn the_default_engine_is_searched_without_an_alias() {
+    let harness = harness();
+    profile(&harness).default_engine("Google").write();
+    let plugin = harness.load("websearch");
+
+    let descriptions: Vec<String> = plugin
+        .matches("ferris")
+        .into_iter()
+        .filter_map(|m| m.description.into_option().map(|d| d.to_string()))
+        .collect();
+    assert_eq!(
+        descriptions,
+        ["Search with Google", "Search with Wikipedia"]
+    );
+
+    // Unknown bangs go to the default engine as well:
+    let matches = plugin.matches("!nope ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Google"
+    );
+}
+
+#[test]
+fn a_user_engine_can_be_the_default() {
+    let harness = harness();
+    profile(&harness).default_engine("Nix Packages").write();
+    let plugin = harness.load("websearch");
+
+    assert_eq!(
+        plugin.matches("ripgrep")[0]
+            .description
+            .clone()
+            .unwrap()
+            .as_str(),
+        "Search with Nix Packages"
+    );
+}
+
+#[test]
+fn selecting_a_search_opens_it_in_a_new_window() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@d ferris");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "--new-window",
+            "https://duckduckgo.com/?q=ferris"
+        ]]
+    );
+}
+
+#[test]
+fn a_trailing_modifier_picks_the_open_mode() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    // The modifier isn't searched for, and it isn't asked for suggestions either:
+    let matches = plugin.matches("@d ferris !p");
+    assert_eq!(matches[0].title.as_str(), "ferris");
+
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "--private-window",
+            "https://duckduckgo.com/?q=ferris"
+        ]]
+    );
+}
+
+#[test]
+fn the_open_mode_comes_from_the_common_config() {
+    let harness = harness();
+    harness.config(
+        "Common.ron",
+        "CommonConfig(browser: Some(BrowserConfig(open_mode: Some(Tab))))",
+    );
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@d ferris");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--new-tab", "https://duckduckgo.com/?q=ferris"]]
+    );
+}
+
+#[test]
+fn searches_can_open_in_another_profile() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            browser: Some(BrowserConfig(modifiers: Some({"!work": Profile("work")}))),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+
+    // Only the configured modifiers are known, so "!p" is searched for:
+    assert_eq!(plugin.titles("@d ferris !p"), ["ferris !p"]);
+
+    let matches = plugin.matches("@d ferris !work");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "-P",
+            "work",
+            "--new-window",
+            "https://duckduckgo.com/?q=ferris"
+        ]]
+    );
+}
+
+#[test]
+fn the_prefix_is_stripped() {
+    let harness = harness();
+    harness.config("Websearch.ron", r#"Config(prefix: Some("?"))"#);
+    let plugin = harness.load("websearch");
+
+    assert!(plugin.matches("@d ferris").is_empty());
+    assert_eq!(plugin.titles("? @d ferris"), ["ferris"]);
+}
+
+#[test]
+fn search_terms_are_percent_encoded() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@d c++ & rust #1 ünïcode");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://duckduckgo.com/?q=c%2B%2B+%26+rust+%231+%C3%BCn%C3%AFcode"
+    );
+}
+
+#[test]
+fn template_params_are_expanded() {
+    let harness = harness();
+    harness
+        .firefox_profile("abcd1234.default")
+        .engine(
+            "Searx",
+            Some("@s"),
+            "https://searx.example.com/{moz:locale}/search/{searchTerms}",
+            &[
+                ("lang", "{language}"),
+                ("ie", "{inputEncoding}"),
+                ("page", "{startPage?}"),
+                ("unknown", "{foo:bar?}"),
+            ],
+        )
+        .query_charset("windows-1252")
+        .write();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@s café au lait");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://searx.example.com/en-US/search/caf%E9%20au%20lait\
+        ?lang=en-US&ie=windows-1252&page=1&unknown="
+    );
+}
+
+// An engine with a suggestions URL on a local stand-in server:
+fn harness_with_suggestions(server: &SuggestionServer) -> Harness {
+    let harness = harness();
+    harness
+        .firefox_profile("abcd1234.default")
+        .engine(
+            "Crates",
+            Some("@c"),
+            "https://crates.io/search",
+            &[("q", "{searchTerms}")],
+        )
+        .suggestions(&server.template())
+        .write();
+    harness.config(
+        "Websearch.ron",
+        "Config(suggestions: Some(SuggestionsConfig(enabled: Some(true))))",
+    );
+    harness
+}
+
+#[test]
+fn suggestions_are_shown_after_the_search() {
+    let server = SuggestionServer::new(&[
+        "serde",
+        "serde_json",
+        "serde_yaml",
+        "serde_with",
+        "serde_derive",
+    ]);
+    let harness = harness_with_suggestions(&server);
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@c serde");
+    let titles: Vec<&str> = matches.iter().map(|m| m.title.as_str()).collect();
+    // The suggestion which is the same as the input is left out, and only 3 are shown:
+    assert_eq!(titles, ["serde", "serde_json", "serde_yaml", "serde_with"]);
+    assert!(matches
+        .iter()
+        .all(|m| m.description.clone().unwrap().as_str() == "Search with Crates"));
+    assert_eq!(server.requests(), ["/?q=serde"]);
+}
+
+#[test]
+fn selecting_a_suggestion_searches_for_it() {
+    let server = SuggestionServer::new(&["serde json"]);
+    let harness = harness_with_suggestions(&server);
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@c serde");
+    plugin.select(&matches[1]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "--new-window",
+            "https://crates.io/search?q=serde+json"
+        ]]
+    );
+}
+
+#[test]
+fn suggestions_can_be_turned_off() {
+    let server = SuggestionServer::new(&["serde_json"]);
+    let harness = harness_with_suggestions(&server);
+    harness.config(
+        "Websearch.ron",
+        "Config(suggestions: Some(SuggestionsConfig(enabled: Some(false))))",
+    );
+    let plugin = harness.load("websearch");
+
+    assert_eq!(plugin.titles("@c serde"), ["serde"]);
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn an_unreachable_engine_only_drops_the_suggestions() {
+    let server = SuggestionServer::new(&[]);
+    let template = server.template();
+    // Nothing listens on the port anymore once the server is gone:
+    drop(server);
+    let harness = harness();
+    harness
+        .firefox_profile("abcd1234.default")
+        .engine(
+            "Crates",
+            Some("@c"),
+            "https://crates.io/search",
+            &[("q", "{searchTerms}")],
+        )
+        .suggestions(&template)
+        .write();
+    harness.config(
+        "Websearch.ron",
+        "Config(suggestions: Some(SuggestionsConfig(enabled: Some(true))))",
+    );
+    let plugin = harness.load("websearch");
+
+    assert_eq!(plugin.titles("@c serde"), ["serde"]);
+}
+
+#[test]
+fn suggestions_are_off_by_default() {
+    let server = SuggestionServer::new(&["serde_json"]);
+    let harness = harness_with_suggestions(&server);
+    harness.config("Websearch.ron", "Config()");
+    let plugin = harness.load("websearch");
+
+    assert_eq!(plugin.titles("@c serde"), ["serde"]);
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn an_unreachable_engine_doesnt_stop_the_suggestions_of_the_others() {
+    let gone = SuggestionServer::new(&[]);
+    let gone_template = gone.template();
+    drop(gone);
+    let server = SuggestionServer::new(&["serde_json"]);
+    let harness = harness_with_suggestions(&server);
+    profile(&harness)
+        .engine(
+            "Gone",
+            Some("@g"),
+            "https://gone.example.com/search",
+            &[("q", "{searchTerms}")],
+        )
+        .suggestions(&gone_template)
+        .engine(
+            "Crates",
+            Some("@c"),
+            "https://crates.io/search",
+            &[("q", "{searchTerms}")],
+        )
+        .suggestions(&server.template())
+        .write();
+    let plugin = harness.load("websearch");
+
+    assert_eq!(plugin.titles("@g serde"), ["serde"]);
+    assert_eq!(plugin.titles("@c serde"), ["serde", "serde_json"]);
+}
+
+#[test]
+fn a_bang_anywhere_picks_its_engine() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("tokio !gh runtime");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "tokio runtime");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with GitHub"
+    );
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://github.com/search?q=tokio+runtime"
+    );
+}
+
+#[test]
+fn aliases_work_as_bangs() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("ripgrep !np");
+    assert_eq!(matches[0].title.as_str(), "ripgrep");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Nix Packages"
+    );
+}
+
+#[test]
+fn unknown_bangs_are_searched_with_the_default_engine() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("!nope ferris");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "!nope ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Wikipedia"
+    );
+}
+
+#[test]
+fn engines_can_be_added_in_the_config() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(engines: Some([
+            EngineConfig(name: "Crates", alias: Some("@c"), url: "https://crates.io/search?q={}"),
+        ]))"#,
+    );
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@c serde json");
+    assert_eq!(matches[0].title.as_str(), "serde json");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://crates.io/search?q=serde+json"
+    );
+}
+
+#[test]
+fn urls_in_the_config_are_not_expanded() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            engines: Some([
+                EngineConfig(name: "Ledger", alias: Some("@l"), url: "https://example.com/$HOME?q={}"),
+            ]),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@l rent");
+    plugin.select(&matches[0]);
+    assert_eq!(harness.launched()[0][2], "https://example.com/$HOME?q=rent");
+}
+
+#[test]
+fn a_match_of_an_engine_which_is_gone_after_a_reload_is_ignored() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            engines: Some([
+                EngineConfig(name: "Crates", alias: Some("@c"), url: "https://crates.io/search?q={}"),
+            ]),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+    let matches = plugin.matches("@c serde");
+
+    harness.config(
+        "Websearch.ron",
+        "Config(suggestions: Some(SuggestionsConfig(enabled: Some(false))))",
+    );
+    plugin.matches("@d serde");
+    assert!(matches!(plugin.select(&matches[0]), HandleResult::Close));
+    assert!(harness.launched().is_empty());
+}
+
+#[test]
+fn an_engine_is_not_shadowed_by_a_group_with_its_name() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            engines: Some([
+                EngineConfig(name: "Rust", alias: Some("@r"), url: "https://lib.rs/search?q={}"),
+            ]),
+            groups: Some([
+                GroupConfig(name: "Rust", alias: "@rs", engines: ["Docs.rs", "crates.io"]),
+            ]),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@r tokio");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [["firefox", "--new-window", "https://lib.rs/search?q=tokio"]]
+    );
+
+    let matches = plugin.matches("@rs tokio");
+    plugin.select(&matches[0]);
+    assert_eq!(harness.launched()[1].len(), 5);
+}
+
+#[test]
+fn bang_files_extend_the_bundled_bangs() {
+    let harness = harness();
+    let ron = harness.config_dir().join("bangs.ron");
+    let json = harness.config_dir().join("bangs.json");
+    harness.config(
+        "bangs.ron",
+        r#"[(name: "Lib.rs", alias: Some("!lr"), url: "https://lib.rs/search?q={}")]"#,
+    );
+    harness.config(
+        "bangs.json",
+        r#"[{"c":"Tech","d":"github.com","r":0,"s":"Sourcehut","sc":"Programming","t":"gh","u":"https://sr.ht/projects?search={{{s}}}"}]"#,
+    );
+
+    harness.config(
+        "Websearch.ron",
+        &format!("Config(bang_file: Some({:?}))", ron.display()),
+    );
+    let plugin = harness.load("websearch");
+    let matches = plugin.matches("!lr tokio");
+    plugin.select(&matches[0]);
+    assert_eq!(harness.launched()[0][2], "https://lib.rs/search?q=tokio");
+
+    // A bang from the file replaces the bundled one with the same trigger:
+    harness.config(
+        "Websearch.ron",
+        &format!("Config(bang_file: Some({:?}))", json.display()),
+    );
+    let matches = plugin.matches("!gh tokio");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Sourcehut"
+    );
+}
+
+// Every version of search.json has a fixture with the same engines: Google, which ships with
+// Firefox and is the default, and "DuckDuckGo Lite" with the keyword "@d".
+fn check_search_json(fixture: &str) {
+    let harness = harness();
+    profile(&harness).search_json(fixture).write();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@d ferris");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with DuckDuckGo Lite"
+    );
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0][2],
+        "https://lite.duckduckgo.com/lite/?q=ferris"
+    );
+
+    assert_eq!(
+        plugin.matches("ferris")[0]
+            .description
+            .clone()
+            .unwrap()
+            .as_str(),
+        "Search with Google"
+    );
+}
+
+macro_rules! search_json_versions {
+    ($($test:ident: $file:literal,)*) => {
+        $(
+            #[test]
+            fn $test() {
+                check_search_json(include_str!(concat!("fixtures/search-json/", $file)));
+            }
+        )*
+    };
+}
+
+search_json_versions! {
+    search_json_version_1: "v1.json",
+    search_json_version_2: "v2.json",
+    search_json_version_3: "v3.json",
+    search_json_version_4: "v4.json",
+    search_json_version_5: "v5.json",
+    search_json_version_6: "v6.json",
+    search_json_version_7: "v7.json",
+    search_json_version_8: "v8.json",
+    search_json_version_9: "v9.json",
+    search_json_version_10: "v10.json",
+    // Newer than any version the plugin knows about, with a broken engine in it:
+    search_json_version_12: "v12.json",
+}
+
+#[test]
+fn a_broken_search_json_is_a_diagnostic() {
+    let harness = harness();
+    profile(&harness)
+        .search_json(r#"{"version": 10, "engines": {"google": {}}}"#)
+        .write();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("ferris");
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0]
+        .description
+        .clone()
+        .unwrap()
+        .contains("Failed while parsing search.json"));
+}
+
+const DOCS_GROUP: &str = r#"Config(
+    suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+    groups: Some([
+        GroupConfig(
+            name: "Rust docs",
+            alias: "@docs",
+            engines: ["Docs.rs", "crates.io", "DuckDuckGo"],
+            separate_windows: Some(SEPARATE),
+        ),
+    ]),
+)"#;
+
+#[test]
+fn a_group_searches_with_all_of_its_engines_at_once() {
+    let harness = harness();
+    harness.config("Websearch.ron", &DOCS_GROUP.replace("SEPARATE", "false"));
+    let plugin = harness.load("websearch");
+
+    // The group comes before the engine with the alias "@d":
+    let matches = plugin.matches("@docs serde");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "serde");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Rust docs"
+    );
+
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "firefox",
+            "--new-window",
+            "https://docs.rs/releases/search?query=serde",
+            "--new-tab",
+            "https://crates.io/search?q=serde",
+            "--new-tab",
+            "https://duckduckgo.com/?q=serde",
+        ]]
+    );
+}
+
+#[test]
+fn a_group_opens_private_windows() {
+    let harness = harness();
+    harness.config("Websearch.ron", &DOCS_GROUP.replace("SEPARATE", "false"));
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@docs serde !p");
+    assert_eq!(matches[0].title.as_str(), "serde");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched()[0]
+            .iter()
+            .filter(|arg| arg.as_str() == "--private-window")
+            .count(),
+        3
+    );
+}
+
+#[test]
+fn a_group_can_open_separate_windows() {
+    let harness = harness();
+    harness.config("Websearch.ron", &DOCS_GROUP.replace("SEPARATE", "true"));
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("@docs serde");
+    plugin.select(&matches[0]);
+    let launched = harness.launched();
+    assert_eq!(launched.len(), 1);
+    assert_eq!(
+        launched[0]
+            .iter()
+            .filter(|arg| arg.as_str() == "--new-window")
+            .count(),
+        3
+    );
+}
+
+#[test]
+fn a_group_with_an_unknown_engine_is_a_diagnostic() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(groups: Some([
+            GroupConfig(name: "Nope", alias: "@nope", engines: ["Nonexistent"]),
+        ]))"#,
+    );
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("ferris");
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0]
+        .description
+        .clone()
+        .unwrap()
+        .contains("unknown engine \"Nonexistent\""));
+}
+
+// Chromium is the default browser and its "Default" profile has the engines of its settings:
+fn chromium_harness() -> Harness {
+    let harness = Harness::new();
+    harness
+        .desktop_file(
+            "chromium.desktop",
+            "[Desktop Entry]\nType=Application\nName=Chromium\nIcon=chromium\nExec=chromium %U\n",
+        )
+        .default_browser("chromium.desktop")
+        .config(
+            "Websearch.ron",
+            "Config(suggestions: Some(SuggestionsConfig(enabled: Some(false))))",
+        );
+    harness
+        .chromium_profile("chromium", "Default")
+        .engine(
+            "Google",
+            "google.com",
+            "{google:baseURL}search?q={searchTerms}&{google:RLZ}sourceid=chrome&ie={inputEncoding}",
+        )
+        .suggestions(
+            "{google:baseSuggestURL}search?client={google:suggestClient}&xssi=t&q={searchTerms}",
+        )
+        .engine("DuckDuckGo", "d", "https://duckduckgo.com/?q={searchTerms}")
+        .engine(
+            "Bookmarks",
+            "@bookmarks",
+            "chrome://bookmarks/?q={searchTerms}",
+        )
+        .default_engine("Google")
+        .write();
+    harness
+}
+
+#[test]
+fn chromium_keywords_pick_their_engine() {
+    let harness = chromium_harness();
+    let plugin = harness.load("websearch");
+
+    let matches = plugin.matches("d ferris");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].title.as_str(), "ferris");
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with DuckDuckGo"
+    );
+
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "chromium",
+            "--new-window",
+            "https://duckduckgo.com/?q=ferris"
+        ]]
+    );
+}
+
+#[test]
+fn chromium_searches_with_its_default_engine_without_a_keyword() {
+    let harness = chromium_harness();
+    let plugin = harness.load("websearch");
+
+    // The starter pack searches the browser itself, so "@bookmarks" isn't a keyword:
+    let matches = plugin.matches("@bookmarks ferris");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].description.clone().unwrap().as_str(),
+        "Search with Google"
+    );
+
+    // Google's own parameters are filled in or left out:
+    let matches = plugin.matches("ferris crab !p");
+    plugin.select(&matches[0]);
+    assert_eq!(
+        harness.launched(),
+        [[
+            "chromium",
+            "--incognito",
+            "https://www.google.com/search?q=ferris+crab&sourceid=chrome&ie=UTF-8"
+        ]]
+    );
+}
+
+#[test]
+fn chromium_engines_are_read_while_web_data_is_locked() {
+    let harness = chromium_harness();
+    let web_data = harness.home().join(".config/chromium/Default/Web Data");
+    let _lock = DatabaseLock::new(&web_data);
+    let plugin = harness.load("websearch");
+
+    assert_eq!(
+        plugin.matches("d ferris")[0]
+            .description
+            .clone()
+            .unwrap()
+            .as_str(),
+        "Search with DuckDuckGo"
+    );
+}
+
+// The first bytes of every PNG, which is enough of an image for the icon cache:
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn icon(harness: &Harness, input: &str) -> String {
+    let matches = harness.load("websearch").matches(input);
+    matches[0].icon.clone().unwrap().to_string()
+}
+
+#[test]
+fn icons_are_taken_from_search_json() {
+    let harness = harness();
+    profile(&harness)
+        .engine(
+            "crates.io",
+            Some("@c"),
+            "https://crates.io/search",
+            &[("q", "{searchTerms}")],
+        )
+        .icon("data:image/png;base64,iVBORw0KGgo=")
+        .write();
+
+    let icon = icon(&harness, "@c serde");
+    let cache = harness.home().join(".cache/anyrun-plugins/websearch-icons");
+    assert!(icon.starts_with(cache.to_str().unwrap()));
+    assert!(icon.ends_with(".png"));
+    assert_eq!(std::fs::read(&icon).unwrap(), PNG);
+}
+
+#[test]
+fn icons_are_taken_from_chromiums_favicons() {
+    let harness = chromium_harness();
+    harness
+        .chromium_profile("chromium", "Default")
+        .engine("DuckDuckGo", "d", "https://duckduckgo.com/?q={searchTerms}")
+        .favicon("https://duckduckgo.com/favicon.ico", PNG)
+        .write();
+
+    let icon = icon(&harness, "d ferris");
+    assert!(icon.ends_with(".png"));
+    assert_eq!(std::fs::read(&icon).unwrap(), PNG);
+}
+
+#[test]
+fn icons_from_the_config_replace_the_browsers() {
+    let harness = harness();
+    profile(&harness)
+        .icon("data:image/png;base64,iVBORw0KGgo=")
+        .write();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            engines: Some([
+                EngineConfig(name: "Docs.rs", alias: Some("@rs"), url: "https://docs.rs/{}", icon: Some("docs")),
+            ]),
+            icons: Some({"Wikipedia": "wikipedia", "GitHub": "github"}),
+        )"#,
+    );
+
+    // Wikipedia is the engine with the icon from search.json, GitHub is a bundled bang:
+    assert_eq!(icon(&harness, "ferris"), "wikipedia");
+    assert_eq!(icon(&harness, "ferris !gh"), "github");
+    assert_eq!(icon(&harness, "@rs serde"), "docs");
+}
+
+// Selects the first match for the input, the way the searches get into the history:
+fn search(plugin: &Plugin, input: &str) {
+    let matches = plugin.matches(input);
+    plugin.select(&matches[0]);
+}
+
+#[test]
+fn selected_searches_are_shown_again_for_their_engine() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+    search(&plugin, "@d ferris");
+    search(&plugin, "@d rust book");
+    search(&plugin, "@np ripgrep");
+
+    // The history outlives the plugin, and an alias alone shows it newest first:
+    let plugin = harness.load("websearch");
+    let matches = plugin.matches("@d");
+    let titles: Vec<&str> = matches.iter().map(|m| m.title.as_str()).collect();
+    assert_eq!(titles, ["rust book", "ferris"]);
+    assert!(matches
+        .iter()
+        .all(|m| m.description.clone().unwrap().as_str() == "Search with DuckDuckGo"));
+    assert_eq!(plugin.titles("@np"), ["ripgrep"]);
+
+    plugin.select(&matches[1]);
+    assert_eq!(
+        harness.launched()[3],
+        [
+            "firefox",
+            "--new-window",
+            "https://duckduckgo.com/?q=ferris"
+        ]
+    );
+}
+
+#[test]
+fn earlier_searches_are_fuzzy_matched() {
+    let harness = harness();
+    let plugin = harness.load("websearch");
+    search(&plugin, "@d rust book");
+    search(&plugin, "@d ferris");
+    search(&plugin, "@d rustup");
+
+    // The typed search comes first, and the one which was already searched for isn't repeated:
+    let titles = plugin.titles("@d rst");
+    assert_eq!(titles[0], "rst");
+    assert_eq!(titles.len(), 3);
+    assert!(titles.contains(&"rustup".to_string()));
+    assert!(titles.contains(&"rust book".to_string()));
+    assert_eq!(plugin.titles("@d ferris"), ["ferris"]);
+
+    // The default engine has a history as well:
+    search(&plugin, "crab");
+    assert_eq!(plugin.titles("cra"), ["cra", "crab"]);
+}
+
+#[test]
+fn excluded_engines_and_private_searches_are_not_remembered() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            history: Some(HistoryConfig(excluded: Some(["DuckDuckGo"]))),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+    search(&plugin, "@d ferris");
+    search(&plugin, "@np ripgrep !p");
+
+    // Without any history, the alias alone is just an empty search:
+    assert!(plugin.titles("@d").is_empty());
+    assert!(plugin.titles("@np").is_empty());
+}
+
+#[test]
+fn the_history_keeps_the_newest_searches() {
+    let harness = harness();
+    harness.config(
+        "Websearch.ron",
+        r#"Config(
+            suggestions: Some(SuggestionsConfig(enabled: Some(false))),
+            history: Some(HistoryConfig(size: Some(2))),
+        )"#,
+    );
+    let plugin = harness.load("websearch");
+    search(&plugin, "@d one");
+    search(&plugin, "@d two");
+    search(&plugin, "@d one");
+    assert_eq!(plugin.titles("@d"), ["one", "two"]);
+
+    search(&plugin, "@d three");
+    assert_eq!(plugin.titles("@d"), ["three", "one"]);
+}
+
+#[test]
+fn groups_have_a_history_of_their_own() {
+    let harness = harness();
+    harness.config("Websearch.ron", &DOCS_GROUP.replace("SEPARATE", "false"));
+    let plugin = harness.load("websearch");
+    search(&plugin, "@docs serde");
+
+    assert_eq!(plugin.titles("@docs"), ["serde"]);
+    assert!(plugin.titles("@d").is_empty());
+}
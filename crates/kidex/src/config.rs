@@ -0,0 +1,41 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    /// The maximum number of matches.
+    max_entries: Option<usize>,
+    /// What is shown on a blank input.
+    bib: Option<common::Bib>,
+    /// How the input is matched against the paths.
+    matching: Option<common::MatchingConfig>,
+}
+
+impl common::SourceConfig for Config {
+    fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("")
+    }
+    fn bib(&self) -> &common::Bib {
+        self.bib.as_ref().unwrap_or(&common::Bib::None)
+    }
+    fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(3)
+    }
+    // The whole path is matched, so a match right after a "/" counts for more:
+    fn matcher(&self) -> common::Matcher {
+        self.matching.clone().unwrap_or_default().matcher().paths()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some(String::default()),
+            max_entries: Some(3),
+            bib: Some(common::Bib::None),
+            matching: Some(common::MatchingConfig::default()),
+        }
+    }
+}
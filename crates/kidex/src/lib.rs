@@ -1,68 +1,78 @@
 use abi_stable::std_types::{ROption, RString, RVec};
 use anyrun_plugin::{anyrun_interface::HandleResult, *};
-use fuzzy_matcher::FuzzyMatcher;
+use common::SourceConfig;
 use kidex_common::IndexEntry;
-use serde::Deserialize;
-use std::{fs, os::unix::prelude::OsStrExt, process::Command};
+use std::{
+    borrow::Cow,
+    os::unix::prelude::OsStrExt,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
+mod config;
 mod util;
 
-#[derive(Deserialize)]
-struct Config {
-    max_entries: usize,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self { max_entries: 3 }
-    }
-}
+use config::Config;
 
 #[handler]
 pub fn handler(selection: Match, state: &mut InitData) -> HandleResult {
-    match &state.selection {
+    if let Some(result) = state.config.handle(&selection, &state.common_config) {
+        return result;
+    }
+    if let Some(result) = state.common_config.handle(&selection, &state.common_config) {
+        return result;
+    }
+
+    let selected = state.index.selected().clone();
+    match selected {
         Some(index_entry) => match selection.id.unwrap().into() {
             util::IndexAction::Open => {
-                if let Err(why) = Command::new("xdg-open").arg(&index_entry.path).spawn() {
+                if let Err(why) = state
+                    .common_config
+                    .launcher("xdg-open")
+                    .arg(&index_entry.path)
+                    .spawn()
+                {
                     println!("Error running xdg-open: {}", why);
                 }
                 HandleResult::Close
             }
             util::IndexAction::CopyPath => {
-                HandleResult::Copy(index_entry.path.clone().into_os_string().as_bytes().into())
+                HandleResult::Copy(index_entry.path.into_os_string().as_bytes().into())
             }
             util::IndexAction::Back => {
-                state.selection = None;
+                *state.index.selected() = None;
                 HandleResult::Refresh(false)
             }
         },
-        None => {
-            let (_, index_entry) = state
-                .index
-                .iter()
-                .find(|(id, _)| selection.id == ROption::RSome(*id as u64))
-                .unwrap();
-
-            state.selection = Some(index_entry.clone());
-            HandleResult::Refresh(true)
-        }
+        None => common::source::handle(&state.index, &selection, &state.common_config),
     }
 }
 
-struct InitData {
-    config: Config,
-    index: Vec<(usize, IndexEntry)>,
-    selection: Option<IndexEntry>,
+pub struct InitData {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+    index: Index,
+}
+
+// The files and directories kidex knows about. Selecting one of them shows what can be
+// done with it instead of the matches, until "Back" is selected:
+struct Index {
+    entries: Vec<IndexEntry>,
+    selected: Mutex<Option<IndexEntry>>,
+}
+
+impl Index {
+    fn selected(&self) -> MutexGuard<'_, Option<IndexEntry>> {
+        self.selected.lock().unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
 #[init]
 pub fn init(config_dir: RString) -> InitData {
-    let config = match fs::read_to_string(format!("{}/kidex.ron", config_dir)) {
-        Ok(content) => ron::from_str(&content).unwrap_or_default(),
-        Err(_) => Config::default(),
-    };
-    let index = match kidex_common::util::get_index(None) {
-        Ok(index) => index.into_iter().enumerate().collect(),
+    // The file has always been called kidex.ron, unlike the other plugins' ones:
+    let config = common::ConfigFile::load(&config_dir, "Kidex", "kidex.ron");
+    let entries = match kidex_common::util::get_index(None) {
+        Ok(index) => index,
         Err(why) => {
             println!("Failed to get kidex index: {}", why);
             Vec::new()
@@ -70,17 +80,33 @@ pub fn init(config_dir: RString) -> InitData {
     };
     InitData {
         config,
-        index,
-        selection: None,
+        common_config: common::common_config(&config_dir, "Kidex"),
+        index: Index {
+            entries,
+            selected: Mutex::new(None),
+        },
     }
 }
 
 #[get_matches]
-pub fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
-    match &data.selection {
+pub fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    data.config.reload();
+    data.common_config.reload();
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if data.index.selected().is_none() && input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
+    }
+    matches
+}
+
+fn matches(input: RString, data: &InitData) -> RVec<Match> {
+    match &*data.index.selected() {
         Some(index_entry) => {
             let path = index_entry.path.to_string_lossy();
-            vec![
+            RVec::from(vec![
                 Match {
                     title: "Open File".into(),
                     description: ROption::RSome(path.clone().into()),
@@ -102,49 +128,65 @@ pub fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
                     id: ROption::RSome(util::IndexAction::Back as u64),
                     icon: ROption::RSome("edit-undo".into()),
                 },
-            ]
-            .into()
+            ])
         }
-        None => {
-            let matcher = fuzzy_matcher::skim::SkimMatcherV2::default().smart_case();
-            let mut index = data
-                .index
-                .clone()
-                .into_iter()
-                .filter_map(|(id, index_entry)| {
-                    matcher
-                        .fuzzy_match(&index_entry.path.as_os_str().to_string_lossy(), &input)
-                        .map(|val| (index_entry, id, val))
-                })
-                .collect::<Vec<_>>();
-
-            index.sort_by(|a, b| b.2.cmp(&a.2));
-
-            index.truncate(data.config.max_entries);
-            index
-                .into_iter()
-                .map(|(entry_index, id, _)| Match {
-                    title: entry_index
-                        .path
-                        .file_name()
-                        .map(|name| name.to_string_lossy().into())
-                        .unwrap_or("N/A".into()),
-                    description: entry_index
-                        .path
-                        .parent()
-                        .map(|path| path.display().to_string().into())
-                        .into(),
-                    use_pango: false,
-                    icon: ROption::RSome(if entry_index.directory {
-                        "folder".into()
-                    } else {
-                        "text-x-generic".into()
-                    }),
-                    id: ROption::RSome(id as u64),
-                })
-                .collect()
+        None => common::source::matches(&data.index, &*data.config, &input),
+    }
+}
+
+impl common::ItemSource for Index {
+    type Item = IndexEntry;
+
+    fn items(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    fn title<'a>(&'a self, index_entry: &'a IndexEntry) -> Cow<'a, str> {
+        match index_entry.path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => index_entry.path.to_string_lossy(),
         }
     }
+
+    fn description<'a>(&'a self, index_entry: &'a IndexEntry) -> Option<Cow<'a, str>> {
+        index_entry
+            .path
+            .parent()
+            .map(|parent| parent.to_string_lossy())
+    }
+
+    fn icon<'a>(&'a self, index_entry: &'a IndexEntry) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(if index_entry.directory {
+            "folder"
+        } else {
+            "text-x-generic"
+        }))
+    }
+
+    // The whole path is matched, and it is split back into the directory, the separator and
+    // the file name for highlighting them in their own places:
+    fn matched<'a>(&'a self, index_entry: &'a IndexEntry) -> Vec<Cow<'a, str>> {
+        let path = index_entry.path.to_string_lossy();
+        let (Some(parent), Some(name)) = (
+            self.description(index_entry),
+            index_entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy()),
+        ) else {
+            return vec![path];
+        };
+        let separator = path
+            .get(parent.len()..path.len() - name.len())
+            .unwrap_or_default()
+            .to_string();
+        vec![parent, Cow::Owned(separator), name]
+    }
+
+    fn activate(&self, index_entry: &IndexEntry, _: &common::CommonConfig) -> HandleResult {
+        *self.selected() = Some(index_entry.clone());
+        HandleResult::Refresh(true)
+    }
 }
 
 #[info]
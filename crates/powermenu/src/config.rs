@@ -0,0 +1,44 @@
+use common::Bib;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    /// What is shown on a blank input.
+    bib: Option<Bib>,
+    /// How the input is matched against the titles.
+    matching: Option<common::MatchingConfig>,
+}
+
+impl common::SourceConfig for Config {
+    fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("!")
+    }
+    fn bib(&self) -> &Bib {
+        self.bib.as_ref().unwrap_or(&Bib::All)
+    }
+    // Every option is shown on a blank input...
+    fn max_entries(&self) -> usize {
+        usize::MAX
+    }
+    // ...but only the best match otherwise, because it makes no sense to display multiple
+    // powermenu options:
+    fn max_matches(&self) -> usize {
+        1
+    }
+    fn matcher(&self) -> common::Matcher {
+        self.matching.clone().unwrap_or_default().matcher()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some("!".to_string()),
+            bib: Some(Bib::All),
+            matching: Some(common::MatchingConfig::default()),
+        }
+    }
+}
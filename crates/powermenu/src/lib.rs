@@ -1,34 +1,11 @@
-use abi_stable::std_types::{RNone, RSome, RString, RVec};
+use abi_stable::std_types::{RString, RVec};
 use anyrun_plugin::*;
-use common::Bib;
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use serde::Deserialize;
-use std::process::Command;
-
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
-    bib: Option<Bib>,
-}
+use common::SourceConfig;
+use std::borrow::Cow;
 
-// QoL methods:
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or("!")
-    }
-    fn bib(&self) -> &Bib {
-        self.bib.as_ref().unwrap_or(&Bib::All)
-    }
-}
+mod config;
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some("!".to_string()),
-            bib: Some(Bib::All),
-        }
-    }
-}
+use config::Config;
 
 struct MenuOption<'a> {
     title: &'a str,
@@ -64,9 +41,17 @@ const MENU_OPTIONS: &[MenuOption] = &[
     },
 ];
 
+struct InitData {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+}
+
 #[init]
-fn init(config_dir: RString) -> Config {
-    common::config(&config_dir, "Powermenu")
+fn init(config_dir: RString) -> InitData {
+    InitData {
+        config: common::config(&config_dir, "Powermenu"),
+        common_config: common::common_config(&config_dir, "Powermenu"),
+    }
 }
 
 #[info]
@@ -78,86 +63,71 @@ fn info() -> PluginInfo {
 }
 
 #[get_matches]
-fn get_matches(input: RString, config: &Config) -> RVec<Match> {
-    // PLUGIN VALIDATION
-    // Early return for the wrong prefix:
-    if !input.starts_with(config.prefix()) {
-        return RVec::new();
+fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    data.config.reload();
+    data.common_config.reload();
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
     }
+    matches
+}
 
-    // MAIN
-    // It is safe to unwrap here because of the early return above.
-    let stripped_input = input.strip_prefix(config.prefix()).unwrap();
-
-    if stripped_input.is_empty() {
-        match config.bib() {
-            Bib::All => {
-                return RVec::from_iter(MENU_OPTIONS.iter().map(|menu_option| Match {
-                    title: RString::from(menu_option.title),
-                    description: RNone,
-                    use_pango: false,
-                    icon: RSome(RString::from(menu_option.icon)),
-                    id: RNone,
-                }))
-            }
-            Bib::None => return RVec::new(),
-            Bib::Currated(v) => {
-                return RVec::from_iter(
-                    MENU_OPTIONS
-                        .iter()
-                        .filter(|menu_option| v.contains(&menu_option.title.to_string()))
-                        .map(|menu_option| Match {
-                            title: RString::from(menu_option.title),
-                            description: RNone,
-                            use_pango: false,
-                            icon: RSome(RString::from(menu_option.icon)),
-                            id: RNone,
-                        }),
-                )
-            }
-        }
+fn matches(input: RString, data: &InitData) -> RVec<Match> {
+    common::source::matches(&PowerMenu, &*data.config, &input)
+}
+
+struct PowerMenu;
+
+impl common::ItemSource for PowerMenu {
+    type Item = MenuOption<'static>;
+
+    fn items(&self) -> &[Self::Item] {
+        MENU_OPTIONS
     }
 
-    let matcher = SkimMatcherV2::default();
-    // Performing fuzzy matching
-    let mut options: Vec<(i64, &MenuOption)> = MENU_OPTIONS
-        .iter()
-        .filter_map(|menu_option| {
-            let score = matcher.fuzzy_match(menu_option.title, stripped_input)?;
-            Some((score, menu_option))
-        })
-        .collect();
-    // Sorting options by score in descending order.
-    options.sort_by(|a, b| b.0.cmp(&a.0));
-    // We want to take only one option, the one with the highest score, because it makes no sense to display multiple powermenu options.
-    let option = options[0].1;
-
-    // SUCCESS
-    return RVec::from(vec![Match {
-        title: RString::from(option.title),
-        description: RNone,
-        use_pango: false,
-        icon: RSome(RString::from(option.icon)),
-        id: RNone,
-    }]);
+    fn title<'a>(&'a self, menu_option: &'a Self::Item) -> Cow<'a, str> {
+        Cow::Borrowed(menu_option.title)
+    }
+
+    fn icon<'a>(&'a self, menu_option: &'a Self::Item) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(menu_option.icon))
+    }
+
+    fn activate(
+        &self,
+        menu_option: &Self::Item,
+        common_config: &common::CommonConfig,
+    ) -> HandleResult {
+        match common_config
+            .launcher(menu_option.command)
+            .arg(menu_option.arg)
+            .spawn()
+        {
+            Ok(_) => (),
+            Err(e) => eprintln!("(Powermenu) Failed while executing command. Closing...\n  {e}"),
+        }
+
+        HandleResult::Close
+    }
 }
 
 #[handler]
-fn handler(selection: Match) -> HandleResult {
-    let selected_option = MENU_OPTIONS
-        .iter()
-        .find(|option| option.title == selection.title)
-        // It is safe to unwrap here because there is no way that the selection title
-        // doesn't match at least one title from MENU_OPTIONS.
-        .unwrap();
-
-    match Command::new(selected_option.command)
-        .arg(selected_option.arg)
-        .spawn()
-    {
-        Ok(_) => (),
-        Err(e) => eprintln!("(Powermenu) Failed while executing command. Closing...\n  {e}"),
+fn handler(selection: Match, data: &InitData) -> HandleResult {
+    let InitData {
+        config,
+        common_config,
+    } = data;
+
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
     }
 
-    HandleResult::Close
+    common::source::handle(&PowerMenu, &selection, common_config)
 }
@@ -0,0 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are calculated.
+    prefix: Option<String>,
+}
+
+impl Config {
+    pub(crate) fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("#")
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some(String::from("#")),
+        }
+    }
+}
@@ -4,29 +4,13 @@ use abi_stable::std_types::{
 };
 use anyrun_plugin::*;
 use rink_core::{ast, date, gnu_units, CURRENCY_FILE};
-use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
-}
-
-impl Config {
-    fn prefix(&self) -> &str {
-        &self.prefix.as_deref().unwrap_or("#")
-    }
-}
+mod config;
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some(String::from("#")),
-        }
-    }
-}
+use config::Config;
 
 struct InitData {
-    config: Config,
+    config: common::ConfigFile<Config>,
     ctx: rink_core::Context,
 }
 
@@ -73,6 +57,7 @@ fn info() -> PluginInfo {
 #[get_matches]
 fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
     let InitData { config, ctx } = data;
+    config.reload();
 
     // VALIDATING PLUGIN
     // Early return when the prefix doesn't match:
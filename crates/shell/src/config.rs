@@ -0,0 +1,30 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are run as commands.
+    prefix: Option<String>,
+    /// The icon of the match.
+    #[serde(default, deserialize_with = "common::expanded")]
+    icon: Option<String>,
+}
+
+// QoL methods so I don't have to chain methods that much:
+impl Config {
+    pub(crate) fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or("$")
+    }
+    pub(crate) fn icon(&self) -> &str {
+        self.icon.as_deref().unwrap_or("utilities-terminal")
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some(String::from("$")),
+            icon: Some(String::from("utilities-terminal")),
+        }
+    }
+}
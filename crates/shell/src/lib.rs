@@ -3,51 +3,34 @@ use abi_stable::std_types::{
     RString, RVec,
 };
 use anyrun_plugin::*;
-use serde::Deserialize;
-use std::{
-    env,
-    process::{self, Command},
-};
+use std::{env, error::Error};
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
-    icon: Option<String>,
-}
+mod config;
 
-// QoL methods so I don't have to chain methods that much:
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or("$")
-    }
-    fn icon(&self) -> &str {
-        self.icon.as_deref().unwrap_or("utilities-terminal")
-    }
-}
+use config::Config;
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some(String::from("$")),
-            icon: Some(String::from("utilities-terminal")),
-        }
-    }
+struct InitData {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+    shell: common::Fallible<String>,
 }
 
-struct InitData {
-    config: Config,
-    shell: String,
+fn load() -> Result<String, Box<dyn Error>> {
+    env::var("SHELL")
+        .map_err(|e| format!("Failed while getting the SHELL env variable:\n    {e}").into())
 }
 
 #[init]
 fn init(config_dir: RString) -> InitData {
     let config = common::config(&config_dir, "Shell");
-    let shell = env::var("SHELL").unwrap_or_else(|e| {
-        eprintln!("(Shell) Failed while getting the SHELL env variable. Closing...:\n  {e}");
-        process::exit(1);
-    });
+    let common_config = common::common_config(&config_dir, "Shell");
+    let shell = common::Fallible::new("Shell", &config_dir, load());
 
-    InitData { config, shell }
+    InitData {
+        config,
+        common_config,
+        shell,
+    }
 }
 
 #[info]
@@ -59,8 +42,25 @@ fn info() -> PluginInfo {
 }
 
 #[get_matches]
-fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
-    let InitData { config, shell } = data;
+fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    data.config.reload();
+    data.common_config.reload();
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
+    }
+    matches
+}
+
+fn matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    let InitData {
+        config,
+        common_config: _,
+        shell,
+    } = data;
 
     // VALIDATING PLUGIN
     // Early return for when the prefix doesn't match:
@@ -77,6 +77,11 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
         return RVec::new();
     }
 
+    let shell = match shell.get_or_retry(load) {
+        Ok(shell) => shell,
+        Err(diagnostic) => return RVec::from(vec![*diagnostic]),
+    };
+
     RVec::from(vec![Match {
         title: stripped_input.trim().into(),
         description: RSome(RString::from(shell.as_str())),
@@ -88,7 +93,25 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
 
 #[handler]
 fn handler(selection: Match, data: &InitData) -> HandleResult {
-    let InitData { config: _, shell } = data;
+    let InitData {
+        config,
+        common_config,
+        shell,
+    } = data;
+
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
+    }
+
+    if let Some(result) = shell.handle(&selection, common_config) {
+        return result;
+    }
+    // It is safe to unwrap here, because only the diagnostic match can be
+    // selected while SHELL is missing.
+    let shell = shell.get().unwrap();
 
     // I mean I don't 100% know if this unwrap will succeed or not...
     // I am guessing that the SHELL env variable always returns a path.
@@ -96,8 +119,9 @@ fn handler(selection: Match, data: &InitData) -> HandleResult {
 
     match shell {
         "bash" => {
-            if let Err(e) = Command::new(shell)
-                .args(["-i", "-c", &selection.title])
+            if let Err(e) = common_config
+                .launcher(shell)
+                .args(&["-i", "-c", &selection.title])
                 .spawn()
             {
                 eprintln!("(Shell) Failed while spawning shell command. Closing...\n  {e}")
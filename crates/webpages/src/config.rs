@@ -0,0 +1,29 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    /// Overrides the browser from Common.ron.
+    browser: Option<common::BrowserConfig>,
+}
+
+// QoL methods
+impl Config {
+    pub(crate) fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn browser(&self) -> Option<&common::BrowserConfig> {
+        self.browser.as_ref()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some(String::default()),
+            browser: None,
+        }
+    }
+}
@@ -3,10 +3,11 @@ use abi_stable::std_types::{
     RString, RVec,
 };
 use anyrun_plugin::*;
-use common::Browser;
-use freedesktop_desktop_entry::DesktopEntry;
-use serde::Deserialize;
-use std::process;
+use std::error::Error;
+
+mod config;
+
+use config::Config;
 
 fn is_valid_page(input: &str) -> Result<bool, regex::Error> {
     // CREATING THE REGEXES
@@ -37,45 +38,37 @@ fn is_valid_page(input: &str) -> Result<bool, regex::Error> {
         || about_re.is_match(input));
 }
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some(String::default()),
-        }
-    }
-}
-
-// QoL methods
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or_default()
-    }
+struct InitData {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+    browser: common::Fallible<Box<dyn common::Browser>>,
+    // How the last query wants its page opened, for the handler, which only gets the match:
+    open_mode: common::OpenMode,
 }
 
-struct InitData {
-    config: Config,
-    common_config: common::CommonConfig,
-    browser_id: String,
+fn load(
+    config: &Config,
+    common_config: &common::CommonConfig,
+) -> Result<Box<dyn common::Browser>, Box<dyn Error>> {
+    let known_browser = common::browser(common_config, config.browser())
+        .map_err(|e| format!("Failed while getting the browser:\n    {e}"))?;
+    Ok(match known_browser {
+        common::KnownBrowser::Firefox(firefox) => Box::new(firefox),
+        common::KnownBrowser::Chromium(chromium) => Box::new(chromium),
+    })
 }
 
 #[init]
 fn init(config_dir: RString) -> InitData {
-    let config = common::config(&config_dir, "Webpages");
+    let config: common::ConfigFile<Config> = common::config(&config_dir, "Webpages");
     let common_config = common::common_config(&config_dir, "Webpages");
-    let browser_id = common::default_browser_id().unwrap_or_else(|e| {
-        eprintln!("(Websearch) Failed while getting the default browser. Closing...\n  {e}");
-        process::exit(1)
-    });
+    let browser = common::Fallible::new("Webpages", &config_dir, load(&config, &common_config));
 
     InitData {
         config,
         common_config,
-        browser_id,
+        browser,
+        open_mode: common::OpenMode::default(),
     }
 }
 
@@ -88,11 +81,33 @@ fn info() -> PluginInfo {
 }
 
 #[get_matches]
-fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
+fn get_matches(input: RString, data: &mut InitData) -> RVec<Match> {
+    // Both files are always checked for changes, hence the non-short-circuiting "|":
+    if data.config.reload() | data.common_config.reload() {
+        let InitData {
+            config,
+            common_config,
+            browser,
+            ..
+        } = data;
+        browser.reload(|| load(config, common_config));
+    }
+
+    let mut matches = matches(input.clone(), data);
+    // Problems in the config files are listed after the matches, so they don't go unnoticed:
+    if input.starts_with(data.config.prefix()) {
+        matches.extend(data.config.diagnostic());
+        matches.extend(data.common_config.diagnostic());
+    }
+    matches
+}
+
+fn matches(input: RString, data: &mut InitData) -> RVec<Match> {
     let InitData {
         config,
-        common_config: _,
-        browser_id,
+        common_config,
+        browser,
+        open_mode,
     } = data;
 
     // VALIDATING PLUGIN
@@ -101,8 +116,12 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
         return RVec::new();
     }
 
+    // A trailing modifier like "!p" isn't part of the page, it only changes how the page opens:
+    let (input, mode) = common::open_mode(&input, common_config, config.browser());
+    *open_mode = mode;
+
     // Early return for when the input isn't a valid page:
-    let is_input_valid_page = match is_valid_page(&input) {
+    let is_input_valid_page = match is_valid_page(input) {
         Ok(v) => v,
         Err(e) => {
             eprintln!("(Webpages) Failed while checking if input is a valid page. Returning no matches...\n  {e}.");
@@ -120,16 +139,18 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
     if stripped_input.is_empty() {
         return RVec::new();
     }
-    
-    // TODO 8.
-    let browser = DesktopEntry::from_appid(browser_id);
-    let name = browser.name::<&str>(&[]).unwrap_or("Desktop Entry".into());
-    let icon = browser.icon().unwrap_or("application-x-executable");
+
+    // Only now the browser is needed, so the diagnostic isn't shown for every input:
+    let browser = match browser.get_or_retry(|| load(config, common_config)) {
+        Ok(browser) => browser,
+        Err(diagnostic) => return RVec::from(vec![*diagnostic]),
+    };
+
     RVec::from(vec![Match {
         title: RString::from(stripped_input),
-        description: RSome(RString::from(format!("Open with {name}"))),
+        description: RSome(RString::from(format!("Open with {}", browser.name()))),
         use_pango: false,
-        icon: RSome(RString::from(icon)),
+        icon: RSome(RString::from(browser.icon())),
         id: RNone,
     }])
 }
@@ -137,21 +158,28 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
 #[handler]
 fn handler(selection: Match, data: &InitData) -> HandleResult {
     let InitData {
-        config: _,
+        config,
         common_config,
-        browser_id,
+        browser,
+        open_mode,
     } = data;
 
-    let browser = match browser_id.as_str() {
-        "firefox" => Box::new(common::Firefox::new(common_config.browser_profile_name())),
-        _ => {
-            eprintln!("(Webpages) Unsupported default browser! Closing...");
-            process::exit(1)
-        }
-    };
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
+    }
+
+    if let Some(result) = browser.handle(&selection, common_config) {
+        return result;
+    }
+    // It is safe to unwrap here, because only the diagnostic match can be
+    // selected while the browser is failing to load.
+    let browser = browser.get().unwrap();
 
     browser
-        .new_window(&selection.title, common_config.prefix_args())
+        .open(&[selection.title.to_string()], open_mode, false, common_config)
         .unwrap_or_else(|e| eprintln!("(Webpages) Failed while opening URL in browser\n  {e}"));
 
     HandleResult::Close
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use crate::{config::EngineConfig, opensearch::Template, Engine};
+
+// A few dozen popular bangs in the format of DuckDuckGo's bang.js, so bangs work without
+// any setup:
+const BUNDLED: &str = include_str!("bangs.json");
+
+// An entry of DuckDuckGo's bang.js. Everything except the trigger, the name and the URL
+// (like the category or the domain) is ignored:
+#[derive(Deserialize)]
+struct DdgBang {
+    t: String,
+    s: String,
+    u: String,
+}
+
+/// The engines which can be picked with a "!bang" anywhere in the input, by their trigger
+/// (the bang without the "!"). Every engine keeps its position, which the matches hold on to.
+pub struct Bangs {
+    engines: Vec<Engine>,
+    triggers: HashMap<String, usize>,
+}
+
+impl Bangs {
+    /// Merges the bundled bangs, the ones in `file` and the aliases of `engines`, in that
+    /// order, so the later ones replace the earlier ones with the same trigger.
+    pub fn load(file: Option<&str>, engines: &[Engine]) -> Result<Self, Box<dyn Error>> {
+        let mut bangs = Bangs {
+            engines: Vec::new(),
+            triggers: HashMap::new(),
+        };
+        bangs.extend(parse_ddg(BUNDLED).expect("The bundled bangs are valid"));
+
+        if let Some(file) = file {
+            let contents = fs::read_to_string(file)
+                .map_err(|e| format!("Failed while reading {file}:\n    {e}"))?;
+            let parsed = match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+                Some("json" | "js") => parse_ddg(&contents),
+                _ => parse_ron(&contents),
+            };
+            bangs.extend(parsed.map_err(|e| format!("Failed while parsing {file}:\n    {e}"))?);
+        }
+
+        // The aliases of the browser's and the config's engines work as bangs as well, e.g.
+        // "@np" as "!np":
+        bangs.extend(engines.iter().cloned());
+        Ok(bangs)
+    }
+
+    /// Finds the first known "!bang" in `input`, wherever it is, and returns its engine together
+    /// with its position and the rest of the input, e.g. GitHub and "tokio runtime" for
+    /// "tokio !gh runtime". Unknown bangs are left in the input, so they are searched for with
+    /// the default engine.
+    pub fn split(&self, input: &str) -> Option<(usize, &Engine, String)> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let (position, index) = words.iter().enumerate().find_map(|(i, word)| {
+            let index = self.triggers.get(&word.strip_prefix('!')?.to_lowercase())?;
+            Some((i, *index))
+        })?;
+
+        let rest: Vec<&str> = words
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != position)
+            .map(|(_, word)| *word)
+            .collect();
+        Some((index, &self.engines[index], rest.join(" ")))
+    }
+
+    /// The engine at the position which `split` returned.
+    pub fn get(&self, index: usize) -> Option<&Engine> {
+        self.engines.get(index)
+    }
+
+    /// The engine with the given name, for the groups, which list their engines by name.
+    pub fn find(&self, name: &str) -> Option<&Engine> {
+        self.engines.iter().find(|engine| engine.name == name)
+    }
+
+    pub fn engines_mut(&mut self) -> impl Iterator<Item = &mut Engine> {
+        self.engines.iter_mut()
+    }
+
+    // An engine with a trigger that is already known takes the place of the old one:
+    fn extend(&mut self, engines: impl IntoIterator<Item = Engine>) {
+        for engine in engines {
+            let Some(trigger) = trigger(&engine.alias) else {
+                continue;
+            };
+            match self.triggers.get(&trigger) {
+                Some(&index) => self.engines[index] = engine,
+                None => {
+                    self.triggers.insert(trigger, self.engines.len());
+                    self.engines.push(engine);
+                }
+            }
+        }
+    }
+}
+
+// The trigger of an alias: "!gh", "@gh" and "gh" are all triggered by "!gh".
+fn trigger(alias: &str) -> Option<String> {
+    let trigger = alias.trim_start_matches(['!', '@']).to_lowercase();
+    (!trigger.is_empty()).then_some(trigger)
+}
+
+fn parse_ddg(contents: &str) -> Result<Vec<Engine>, Box<dyn Error>> {
+    let bangs: Vec<DdgBang> = serde_json::from_str(contents)?;
+    let engines = bangs
+        .into_iter()
+        .map(|bang| {
+            // DuckDuckGo marks the place of the search terms with "{{{s}}}":
+            let url = Template::new(&bang.u.replace("{{{s}}}", "{searchTerms}"));
+            Engine::new(&bang.s, url, None, &bang.t, "")
+        })
+        .collect();
+    Ok(engines)
+}
+
+// The same list of engines as the `engines` field of the config, where the alias is the bang:
+fn parse_ron(contents: &str) -> Result<Vec<Engine>, Box<dyn Error>> {
+    let engines: Vec<EngineConfig> = ron::from_str(contents)?;
+    Ok(engines.iter().map(Engine::from).collect())
+}
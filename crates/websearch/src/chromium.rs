@@ -0,0 +1,180 @@
+use common::{Chromium, DatabaseCopy};
+use rusqlite::{Connection, OptionalExtension};
+use std::{error::Error, fs};
+
+use crate::{icons, opensearch::Template, Engine, SearchEngines};
+
+// A row of the keywords table, which holds every engine of the settings:
+struct Keyword {
+    id: i64,
+    short_name: String,
+    keyword: String,
+    url: String,
+    suggest_url: String,
+    input_encodings: String,
+    sync_guid: String,
+    favicon_url: String,
+}
+
+impl SearchEngines for Chromium {
+    fn search_engines(&self) -> Result<Vec<Engine>, Box<dyn Error>> {
+        let profile_dir = self
+            .profile_dir()
+            .map_err(|e| format!("Failed while getting the profile directory:\n    {e}"))?;
+
+        // The browser keeps Web Data locked for as long as it runs, so a copy of it is read:
+        let copy = DatabaseCopy::new(&profile_dir, "Web Data")
+            .map_err(|e| format!("Failed while copying Web Data:\n    {e}"))?;
+        let conn = Connection::open(copy.path())
+            .map_err(|e| format!("Failed while opening Web Data:\n    {e}"))?;
+        let keywords =
+            keywords(&conn).map_err(|e| format!("Failed while reading Web Data:\n    {e}"))?;
+
+        // Newer versions keep the GUID of the default engine in the Preferences, older ones keep
+        // its id in Web Data:
+        let default_guid = default_guid(&profile_dir);
+        let default_id: Option<i64> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'Default Search Provider ID'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok());
+
+        // The icons are in the Favicons database, which is locked as well. Without it, the
+        // engines simply don't have icons:
+        let favicons = DatabaseCopy::new(&profile_dir, "Favicons")
+            .and_then(|copy| {
+                let conn = Connection::open(copy.path())?;
+                Ok((copy, conn))
+            })
+            .map_err(|e| eprintln!("(Websearch) Failed while reading Favicons:\n  {e}"))
+            .ok();
+
+        // MAPPING TO THE Engine STRUCT
+        let engines = keywords
+            .into_iter()
+            .filter_map(|keyword| {
+                // The starter pack (like "@bookmarks") searches the browser itself:
+                let url = expand_google(&keyword.url);
+                if !url.starts_with("http") {
+                    return None;
+                }
+                let encoding = keyword
+                    .input_encodings
+                    .split(';')
+                    .find(|encoding| !encoding.is_empty())
+                    .unwrap_or("UTF-8");
+                let url = Template::new(&url).input_encoding(encoding);
+                let suggestions = (!keyword.suggest_url.is_empty())
+                    .then(|| Template::new(&expand_google(&keyword.suggest_url)));
+
+                let icon = favicons
+                    .as_ref()
+                    .and_then(|(_, conn)| icon(conn, &keyword))
+                    .unwrap_or_default();
+
+                let mut engine = Engine::new(
+                    &keyword.short_name,
+                    url,
+                    suggestions,
+                    &keyword.keyword,
+                    &icon,
+                );
+                engine.default = match &default_guid {
+                    Some(guid) => *guid == keyword.sync_guid,
+                    None => default_id == Some(keyword.id),
+                };
+                Some(engine)
+            })
+            .collect();
+
+        Ok(engines)
+    }
+}
+
+fn keywords(conn: &Connection) -> Result<Vec<Keyword>, rusqlite::Error> {
+    let mut statement = conn.prepare(
+        "SELECT
+            id,
+            short_name,
+            keyword,
+            url,
+            IFNULL(suggest_url, ''),
+            IFNULL(input_encodings, ''),
+            IFNULL(sync_guid, ''),
+            favicon_url
+        FROM keywords
+        ORDER BY id",
+    )?;
+    let keywords = statement
+        .query_map([], |row| {
+            Ok(Keyword {
+                id: row.get(0)?,
+                short_name: row.get(1)?,
+                keyword: row.get(2)?,
+                url: row.get(3)?,
+                suggest_url: row.get(4)?,
+                input_encodings: row.get(5)?,
+                sync_guid: row.get(6)?,
+                favicon_url: row.get(7)?,
+            })
+        })?
+        .collect();
+    keywords
+}
+
+// The biggest bitmap of the engine's favicon, written to the icon cache:
+fn icon(conn: &Connection, keyword: &Keyword) -> Option<String> {
+    if keyword.favicon_url.is_empty() {
+        return None;
+    }
+    let image: Vec<u8> = conn
+        .query_row(
+            "SELECT b.image_data
+            FROM favicons f
+                JOIN favicon_bitmaps b ON b.icon_id = f.id
+            WHERE f.url = ?1 AND LENGTH(b.image_data) > 0
+            ORDER BY b.width DESC
+            LIMIT 1",
+            [&keyword.favicon_url],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| eprintln!("(Websearch) Failed while reading a favicon:\n  {e}"))
+        .ok()??;
+    // Chromium stores every bitmap as a PNG:
+    icons::cache(&image, "png")
+        .map_err(|e| {
+            eprintln!(
+                "(Websearch) Failed while caching the icon of \"{}\":\n  {e}",
+                keyword.short_name
+            )
+        })
+        .ok()
+}
+
+// The default engine from the Preferences, which are only read if they can be:
+fn default_guid(profile_dir: &str) -> Option<String> {
+    let preferences = fs::read(format!("{profile_dir}/Preferences")).ok()?;
+    let preferences: serde_json::Value = serde_json::from_slice(&preferences).ok()?;
+    preferences["default_search_provider"]["guid"]
+        .as_str()
+        .filter(|guid| !guid.is_empty())
+        .map(str::to_string)
+}
+
+// The engines which ship with the browser use Google's own parameters. The base URLs are
+// filled in, and the rest (like "{google:RLZ}") is left out by the template. The "firefox"
+// client gets suggestions in the plain OpenSearch format instead of Chromium's own one:
+fn expand_google(url: &str) -> String {
+    url.replace("{google:baseURL}", "https://www.google.com/")
+        .replace(
+            "{google:baseSuggestURL}",
+            "https://www.google.com/complete/",
+        )
+        .replace("{google:suggestClient}", "firefox")
+        .replace("xssi=t&", "")
+}
@@ -0,0 +1,216 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub(crate) struct Config {
+    /// Only inputs starting with this are matched.
+    prefix: Option<String>,
+    /// Overrides the browser from Common.ron, e.g. to use the search engines of another profile.
+    browser: Option<common::BrowserConfig>,
+    /// Suggestions from the search engine, shown below the typed search.
+    suggestions: Option<SuggestionsConfig>,
+    /// Engines on top of the browser's, e.g. ones the browser doesn't know about.
+    engines: Option<Vec<EngineConfig>>,
+    /// A list of "!bangs" which extends the bundled one: DuckDuckGo's bang.js (if the file
+    /// ends in ".json") or a RON list of engines like in `engines`, where the alias is the bang.
+    #[serde(default, deserialize_with = "common::expanded")]
+    bang_file: Option<String>,
+    /// Engines which are searched with at once, e.g. all the Rust docs.
+    groups: Option<Vec<GroupConfig>>,
+    /// Icons by the name of the engine (or group), which replace the ones from the browser.
+    /// Either the name of an icon from the icon theme or the path of an image.
+    #[serde(default, deserialize_with = "common::expanded")]
+    icons: Option<BTreeMap<String, String>>,
+    /// Earlier searches, shown again when their engine is picked.
+    history: Option<HistoryConfig>,
+}
+
+impl Config {
+    pub(crate) fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn browser(&self) -> Option<&common::BrowserConfig> {
+        self.browser.as_ref()
+    }
+    pub(crate) fn suggestions(&self) -> SuggestionsConfig {
+        self.suggestions.clone().unwrap_or_default()
+    }
+    pub(crate) fn engines(&self) -> &[EngineConfig] {
+        self.engines.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn bang_file(&self) -> Option<&str> {
+        self.bang_file.as_deref()
+    }
+    pub(crate) fn groups(&self) -> &[GroupConfig] {
+        self.groups.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn icon(&self, name: &str) -> Option<&str> {
+        self.icons.as_ref()?.get(name).map(String::as_str)
+    }
+    pub(crate) fn history(&self) -> HistoryConfig {
+        self.history.clone().unwrap_or_default()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            prefix: Some(String::default()),
+            browser: None,
+            suggestions: Some(SuggestionsConfig::default()),
+            engines: Some(Vec::new()),
+            bang_file: None,
+            groups: Some(Vec::new()),
+            icons: Some(BTreeMap::new()),
+            history: Some(HistoryConfig::default()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub(crate) struct EngineConfig {
+    /// Shown as "Search with <name>".
+    name: String,
+    /// Typed before the search terms to pick this engine, like "@np", and also works as a
+    /// bang ("!np"). Engines without one are searched with by default.
+    alias: Option<String>,
+    /// The URL of the results, with "{}" (or any other OpenSearch parameter, like
+    /// "{searchTerms}") where the search terms go.
+    url: String,
+    /// The name of an icon from the icon theme or the path of an image.
+    #[serde(default, deserialize_with = "common::expanded")]
+    icon: Option<String>,
+}
+
+impl EngineConfig {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+    pub(crate) fn alias(&self) -> &str {
+        self.alias.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+    pub(crate) fn icon(&self) -> &str {
+        self.icon.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub(crate) struct SuggestionsConfig {
+    /// Ask the engine for suggestions while typing. Off by default, so nothing is sent to
+    /// the engine before a search is selected.
+    enabled: Option<bool>,
+    /// How many suggestions are shown at most.
+    max: Option<usize>,
+    /// How long the input has to stay the same before the engine is asked, in milliseconds.
+    delay: Option<u64>,
+    /// How long the engine has to answer, in milliseconds.
+    timeout: Option<u64>,
+}
+
+impl SuggestionsConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+    pub(crate) fn max(&self) -> usize {
+        self.max.unwrap_or(3)
+    }
+    pub(crate) fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay.unwrap_or(150))
+    }
+    pub(crate) fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout.unwrap_or(1000))
+    }
+}
+
+impl Default for SuggestionsConfig {
+    fn default() -> Self {
+        SuggestionsConfig {
+            enabled: Some(false),
+            max: Some(3),
+            delay: Some(150),
+            timeout: Some(1000),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub(crate) struct HistoryConfig {
+    /// Remember the selected searches. With this off, nothing is written to the history.
+    enabled: Option<bool>,
+    /// How many searches are remembered per engine (or group).
+    size: Option<usize>,
+    /// How many earlier searches are shown at most.
+    max: Option<usize>,
+    /// Engines (or groups) by name whose searches are never remembered nor shown.
+    excluded: Option<Vec<String>>,
+    /// How the input is matched against the earlier searches. They are never highlighted,
+    /// because the title is what gets searched for.
+    matching: Option<common::MatchingConfig>,
+}
+
+impl HistoryConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+    pub(crate) fn size(&self) -> usize {
+        self.size.unwrap_or(50)
+    }
+    pub(crate) fn max(&self) -> usize {
+        self.max.unwrap_or(5)
+    }
+    pub(crate) fn excluded(&self) -> &[String] {
+        self.excluded.as_deref().unwrap_or_default()
+    }
+    pub(crate) fn matcher(&self) -> common::Matcher {
+        self.matching.clone().unwrap_or_default().matcher()
+    }
+    // Whether the searches of the engine (or group) with this name are remembered:
+    pub(crate) fn remembers(&self, name: &str) -> bool {
+        self.enabled()
+            && self.size() > 0
+            && !self.excluded().iter().any(|excluded| excluded == name)
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            enabled: Some(true),
+            size: Some(50),
+            max: Some(5),
+            excluded: Some(Vec::new()),
+            matching: Some(common::MatchingConfig::default()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub(crate) struct GroupConfig {
+    /// Shown as "Search with <name>".
+    name: String,
+    /// Typed before the search terms to search with the whole group, like "@docs".
+    alias: String,
+    /// The names of the engines, from the browser, `engines` or the bangs.
+    engines: Vec<String>,
+    /// Open every engine in a window of its own, instead of as tabs of one new window.
+    separate_windows: Option<bool>,
+}
+
+impl GroupConfig {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+    pub(crate) fn alias(&self) -> &str {
+        &self.alias
+    }
+    pub(crate) fn engines(&self) -> &[String] {
+        &self.engines
+    }
+    pub(crate) fn separate_windows(&self) -> bool {
+        self.separate_windows.unwrap_or(false)
+    }
+}
@@ -1,12 +1,17 @@
 use common::Firefox;
-use serde_json::Number;
 use std::{error::Error, fs::File, io::Read};
 
-use crate::{Engine, SearchEngines};
+use crate::{
+    icons,
+    opensearch::Template,
+    search_json::{EngineData, SearchJson, UrlData},
+    Engine, SearchEngines,
+};
 
 impl SearchEngines for Firefox {
-    fn search_engines(&self, profile_name: &str) -> Result<Vec<Engine>, Box<dyn Error>> {
-        let profile_dir = Firefox::profile_dir(profile_name)
+    fn search_engines(&self) -> Result<Vec<Engine>, Box<dyn Error>> {
+        let profile_dir = self
+            .profile_dir()
             .map_err(|e| format!("Failed while getting the profile directory:\n    {e}"))?;
 
         // PROCESSING THE mozlz4 FILE
@@ -18,53 +23,196 @@ impl SearchEngines for Firefox {
         let decompressed = mozlz4::decompress(buffer)
             .map_err(|e| format!("Failed while decompressing the mozlz4 file:\n    {e}"))?;
 
-        let data: serde_json::Value = serde_json::from_slice(&decompressed)
-            .map_err(|e| format!("Failed while parsing JSON:\n    {e}"))?;
+        let search = SearchJson::parse(&decompressed)
+            .map_err(|e| format!("Failed while parsing search.json:\n    {e}"))?;
 
         // MAPPING TO THE Engine STRUCT
-        // The below code sucks ass:
-        let engines = data["engines"]
-            .as_array()
-            .unwrap()
+        let engines = search
+            .engines
             .iter()
             .filter_map(|engine_data| {
-                if engine_data["_isAppProvided"].as_bool() == Some(true) {
-                    return None;
-                }
-
-                let name = engine_data["_name"].as_str()?;
-                let alias = if data["version"].as_number()? == &Number::from(6) {
-                    engine_data
-                        .get("_definedAliases")
-                        .and_then(|v| v.as_array().unwrap()[0].as_str())
-                        .unwrap_or_default()
+                let mut engine = if engine_data.is_app_provided() {
+                    app_provided(engine_data)?
                 } else {
-                    engine_data["_definedAliases"]
-                        .as_array()?
-                        .first()
-                        .and_then(|v| v.as_str())
-                        .unwrap_or_default()
+                    user_engine(engine_data)?
                 };
 
-                let url_data = &engine_data["_urls"].as_array()?[0];
-                let params = url_data["params"]
-                    .as_array()?
-                    .iter()
-                    .map(|param_data| {
-                        let name = param_data["name"].as_str().unwrap();
-                        let value = param_data["value"].as_str().unwrap();
-                        format!("{name}={value}")
-                    })
-                    .collect::<Vec<_>>()
-                    .join("&");
-                let template = url_data["template"].as_str()?;
-
-                let url = format!("{template}?{params}");
-
-                Some(Engine::new(name, &url, alias, ""))
+                // The keyword from the settings replaces the one of an engine which ships
+                // with Firefox:
+                if let Some(alias) = engine_data.alias() {
+                    engine.alias = alias.to_string();
+                }
+                engine.default = search.default_engine_id.as_ref() == Some(&engine_data.id);
+                // Firefox doesn't list hidden engines, unless it searches with them anyway:
+                if engine_data.meta_data.hidden && !engine.default {
+                    return None;
+                }
+                Some(engine)
             })
             .collect();
 
         Ok(engines)
     }
 }
+
+// An engine which the user added, with everything in search.json:
+fn user_engine(engine_data: &EngineData) -> Option<Engine> {
+    // The results page is the URL without a type, and the suggestions (if the engine has any)
+    // are the one with the type of OpenSearch suggestions:
+    let charset = engine_data.query_charset.as_deref().unwrap_or("UTF-8");
+    let url = engine_data
+        .urls
+        .iter()
+        .find(|url_data| matches!(url_data.kind.as_deref(), None | Some("text/html")))
+        .map(|url_data| template(url_data, charset));
+    let Some(url) = url else {
+        eprintln!(
+            "(Websearch) Skipping \"{}\", which doesn't have a search URL",
+            engine_data.name
+        );
+        return None;
+    };
+    let suggestions = engine_data
+        .urls
+        .iter()
+        .find(|url_data| url_data.kind.as_deref() == Some("application/x-suggestions+json"))
+        .map(|url_data| template(url_data, charset));
+
+    // Only icons which are in search.json itself are used, not ones which would have to be
+    // downloaded first:
+    let icon = match engine_data.icon() {
+        Some(icon) if icon.starts_with("data:") => {
+            icons::cache_data_uri(icon).unwrap_or_else(|e| {
+                eprintln!(
+                    "(Websearch) Failed while caching the icon of \"{}\":\n  {e}",
+                    engine_data.name
+                );
+                String::new()
+            })
+        }
+        _ => String::new(),
+    };
+
+    Some(Engine::new(&engine_data.name, url, suggestions, "", &icon))
+}
+
+// An engine which ships with Firefox. search.json only has its name and id, the URLs are
+// taken from the table below:
+fn app_provided(engine_data: &EngineData) -> Option<Engine> {
+    let name = engine_data.name.as_str();
+    let Some(known) = APP_PROVIDED
+        .iter()
+        .find(|known| known.is(&engine_data.id, name))
+    else {
+        eprintln!("(Websearch) Skipping \"{name}\", which ships with Firefox but isn't known to the plugin");
+        return None;
+    };
+
+    let name = if name.is_empty() { known.name } else { name };
+    Some(Engine::new(
+        name,
+        Template::new(known.url),
+        known.suggestions.map(Template::new),
+        known.alias,
+        "",
+    ))
+}
+
+struct AppProvided {
+    // The ids of newer versions (like "ddg") and the start of the ones of older versions
+    // (like "ddg@search.mozilla.orgdefault"):
+    ids: &'static [&'static str],
+    name: &'static str,
+    // The keyword Firefox gives the engine:
+    alias: &'static str,
+    url: &'static str,
+    suggestions: Option<&'static str>,
+}
+
+impl AppProvided {
+    fn is(&self, id: &str, name: &str) -> bool {
+        let id = id.split('@').next().unwrap_or_default();
+        self.ids.contains(&id) || self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+// The engines Firefox ships with in English speaking regions:
+const APP_PROVIDED: &[AppProvided] = &[
+    AppProvided {
+        ids: &["google"],
+        name: "Google",
+        alias: "@google",
+        url: "https://www.google.com/search?client=firefox-b-d&q={searchTerms}",
+        suggestions: Some("https://www.google.com/complete/search?client=firefox&q={searchTerms}"),
+    },
+    AppProvided {
+        ids: &["bing"],
+        name: "Bing",
+        alias: "@bing",
+        url: "https://www.bing.com/search?pc=MOZI&form=MOZLBR&q={searchTerms}",
+        suggestions: Some("https://www.bing.com/osjson.aspx?query={searchTerms}&form=OSDJAS"),
+    },
+    AppProvided {
+        ids: &["ddg"],
+        name: "DuckDuckGo",
+        alias: "@duckduckgo",
+        url: "https://duckduckgo.com/?t=ffab&q={searchTerms}",
+        suggestions: Some("https://ac.duckduckgo.com/ac/?q={searchTerms}&type=list"),
+    },
+    AppProvided {
+        ids: &["wikipedia"],
+        name: "Wikipedia (en)",
+        alias: "@wikipedia",
+        url: "https://en.wikipedia.org/wiki/Special:Search?search={searchTerms}",
+        suggestions: Some(
+            "https://en.wikipedia.org/w/api.php?action=opensearch&search={searchTerms}",
+        ),
+    },
+    AppProvided {
+        ids: &["amazondotcom", "amazondotcom-us"],
+        name: "Amazon.com",
+        alias: "@amazon",
+        url: "https://www.amazon.com/s?k={searchTerms}",
+        suggestions: None,
+    },
+    AppProvided {
+        ids: &["ebay"],
+        name: "eBay",
+        alias: "@ebay",
+        url: "https://www.ebay.com/sch/?_nkw={searchTerms}",
+        suggestions: Some("https://autosug.ebaystatic.com/autosug?sId=0&kwd={searchTerms}&fmt=osr"),
+    },
+    AppProvided {
+        ids: &["ecosia"],
+        name: "Ecosia",
+        alias: "@ecosia",
+        url: "https://www.ecosia.org/search?q={searchTerms}",
+        suggestions: Some("https://ac.ecosia.org/autocomplete?q={searchTerms}&type=list"),
+    },
+    AppProvided {
+        ids: &["qwant"],
+        name: "Qwant",
+        alias: "@qwant",
+        url: "https://www.qwant.com/?q={searchTerms}",
+        suggestions: Some("https://api.qwant.com/api/suggest/?q={searchTerms}&client=opensearch"),
+    },
+    AppProvided {
+        ids: &["perplexity"],
+        name: "Perplexity",
+        alias: "@perplexity",
+        url: "https://www.perplexity.ai/search?q={searchTerms}",
+        suggestions: None,
+    },
+];
+
+// The template of an entry of "_urls", with its params:
+fn template(url_data: &UrlData, charset: &str) -> Template {
+    let params = url_data
+        .params
+        .iter()
+        .filter_map(|param_data| Some((param_data.name.clone(), param_data.value.clone()?)))
+        .collect();
+    Template::new(&url_data.template)
+        .params(params)
+        .input_encoding(charset)
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fs, io::ErrorKind, path::PathBuf};
+
+/// The searches which were selected with every engine (or group), by its name and newest
+/// first. It is kept in `$XDG_STATE_HOME`, so it outlives anyrun.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct History {
+    searches: BTreeMap<String, Vec<String>>,
+}
+
+impl History {
+    /// Reads the history. Without a file, nothing was searched for yet.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = path()?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(History::default()),
+            Err(e) => {
+                return Err(format!("Failed while reading {}:\n    {e}", path.display()).into())
+            }
+        };
+        ron::from_str(&contents)
+            .map_err(|e| format!("Failed while parsing {}:\n    {e}", path.display()).into())
+    }
+
+    /// The searches of the engine, newest first.
+    pub fn searches(&self, engine: &str) -> &[String] {
+        self.searches
+            .get(engine)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Puts the search in front of the engine's searches (a search which was already there
+    /// is moved) and forgets the oldest ones beyond `size`.
+    pub fn add(&mut self, engine: &str, terms: &str, size: usize) {
+        let searches = self.searches.entry(engine.to_string()).or_default();
+        searches.retain(|search| search != terms);
+        searches.insert(0, terms.to_string());
+        searches.truncate(size);
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed while creating {}:\n    {e}", dir.display()))?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        // The file is replaced at once, so a crash while writing doesn't leave half of it:
+        let temp = path.with_extension("ron.tmp");
+        fs::write(&temp, contents)
+            .map_err(|e| format!("Failed while writing {}:\n    {e}", temp.display()))?;
+        fs::rename(&temp, &path)
+            .map_err(|e| format!("Failed while writing {}:\n    {e}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn path() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(common::xdg::state_dir()?.join("websearch-history.ron"))
+}
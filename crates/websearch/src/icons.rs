@@ -0,0 +1,79 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::{error::Error, fs};
+
+/// Writes the image to the icon cache and returns its path, which anyrun shows like any icon.
+/// The file is named after a hash of the image, so an icon which is already there isn't
+/// written again, and engines with the same icon share the file.
+pub fn cache(image: &[u8], extension: &str) -> Result<String, Box<dyn Error>> {
+    let dir = common::xdg::cache_dir()?.join("websearch-icons");
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed while creating {}:\n    {e}", dir.display()))?;
+
+    let path = dir.join(format!("{:016x}.{extension}", fnv1a(image)));
+    if !path.exists() {
+        fs::write(&path, image)
+            .map_err(|e| format!("Failed while writing {}:\n    {e}", path.display()))?;
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Decodes a data URI like "data:image/png;base64,iVBOR..." and caches the image.
+pub fn cache_data_uri(uri: &str) -> Result<String, Box<dyn Error>> {
+    let (header, data) = uri
+        .strip_prefix("data:")
+        .and_then(|uri| uri.split_once(','))
+        .ok_or("The icon isn't a data URI!")?;
+    let mut parts = header.split(';');
+    let mime = parts.next().unwrap_or_default();
+    let image = match parts.any(|part| part == "base64") {
+        true => STANDARD.decode(data.trim())?,
+        false => percent_decode(data),
+    };
+    if image.is_empty() {
+        return Err("The icon is empty!".into());
+    }
+    cache(&image, extension(mime))
+}
+
+// The extension tells GTK what kind of image the file is:
+fn extension(mime: &str) -> &'static str {
+    match mime {
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "png",
+    }
+}
+
+// Data URIs without ";base64" (mostly SVGs) are percent-encoded instead:
+fn percent_decode(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+// A hash which stays the same between versions of Rust, unlike the one of the std:
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
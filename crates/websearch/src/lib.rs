@@ -3,94 +3,280 @@ use abi_stable::std_types::{
     RString, RVec,
 };
 use anyrun_plugin::*;
-use serde::Deserialize;
 use std::{
+    cmp::Reverse,
     error::Error,
-    process::{self},
+    sync::{Mutex, MutexGuard},
 };
 
+mod bangs;
+mod chromium;
+mod config;
 mod firefox;
+mod history;
+mod icons;
+mod opensearch;
+mod search_json;
+mod suggestions;
 
-#[derive(Debug)]
+use bangs::Bangs;
+use config::{Config, EngineConfig, GroupConfig, SuggestionsConfig};
+use history::History;
+use opensearch::Template;
+use suggestions::Suggester;
+
+#[derive(Debug, Clone)]
 struct Engine {
     name: String,
-    url: String,
+    url: Template,
+    // The OpenSearch suggestion URL, if the engine has one:
+    suggestions: Option<Template>,
     alias: String,
+    // An icon name or the path of an image, e.g. one which was taken from the browser and
+    // written to the icon cache:
     icon: String,
+    // Whether the browser searches with this engine when no keyword is typed:
+    default: bool,
 }
 
 impl Engine {
-    pub fn new(name: &str, url: &str, alias: &str, icon: &str) -> Self {
+    pub fn new(
+        name: &str,
+        url: Template,
+        suggestions: Option<Template>,
+        alias: &str,
+        icon: &str,
+    ) -> Self {
         Engine {
             name: name.to_string(),
-            url: url.to_string(),
+            url,
+            suggestions,
             alias: alias.to_string(),
             icon: icon.to_string(),
+            default: false,
         }
     }
-}
 
-trait SearchEngines: common::Browser {
-    // I suspect every single browser would need the profile name as a parameter...
-    fn search_engines(&self, profile_name: &str) -> Result<Vec<Engine>, Box<dyn Error>>;
+    // The match which searches for `terms` with this engine:
+    fn search_match(&self, target: Target, terms: &str) -> Match {
+        search_match(target, &self.name, &self.icon, terms)
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    prefix: Option<String>,
+// Engines which are searched with at once, picked by the alias of the group:
+#[derive(Debug)]
+struct Group {
+    name: String,
+    alias: String,
+    engines: Vec<Engine>,
+    separate_windows: bool,
+    icon: String,
 }
 
-impl Config {
-    fn prefix(&self) -> &str {
-        self.prefix.as_deref().unwrap_or_default()
+impl Group {
+    // The engines are looked up by name, first among the browser's and the config's engines
+    // and then among the bangs:
+    fn new(
+        config: &GroupConfig,
+        engines: &[Engine],
+        bangs: &Bangs,
+        icon: Option<&str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let engines = config
+            .engines()
+            .iter()
+            .map(|name| {
+                engines
+                    .iter()
+                    .find(|engine| &engine.name == name)
+                    .or_else(|| bangs.find(name))
+                    .cloned()
+                    .ok_or_else(|| {
+                        format!(
+                            "The group \"{}\" has an unknown engine \"{name}\"!",
+                            config.name()
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Without an icon of its own, the group looks like its first engine:
+        let icon = match icon {
+            Some(icon) => icon.to_string(),
+            None => engines
+                .first()
+                .map(|engine| engine.icon.clone())
+                .unwrap_or_default(),
+        };
+
+        Ok(Group {
+            name: config.name().to_string(),
+            alias: config.alias().to_string(),
+            engines,
+            separate_windows: config.separate_windows(),
+            icon,
+        })
+    }
+
+    fn search_match(&self, target: Target, terms: &str) -> Match {
+        search_match(target, &self.name, &self.icon, terms)
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            prefix: Some(String::default()),
+// What a match searches with, by its position among the engines, the bangs or the groups. Names
+// aren't unique (a group can have the name of an engine), so the handler gets the position back
+// through the match's id, with the kind in the upper half:
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Engine(usize),
+    Bang(usize),
+    Group(usize),
+}
+
+impl Target {
+    fn id(self) -> u64 {
+        let (kind, index) = match self {
+            Target::Engine(index) => (0, index),
+            Target::Bang(index) => (1, index),
+            Target::Group(index) => (2, index),
+        };
+        kind << 32 | index as u64
+    }
+
+    fn from_id(id: u64) -> Option<Self> {
+        let index = (id & u64::from(u32::MAX)) as usize;
+        match id >> 32 {
+            0 => Some(Target::Engine(index)),
+            1 => Some(Target::Bang(index)),
+            2 => Some(Target::Group(index)),
+            _ => None,
         }
     }
 }
 
-struct InitData {
-    config: Config,
-    common_config: common::CommonConfig,
+fn search_match(target: Target, name: &str, icon: &str, terms: &str) -> Match {
+    Match {
+        title: RString::from(terms),
+        description: RSome(RString::from(format!("Search with {name}"))),
+        use_pango: false,
+        icon: RSome(RString::from(icon)),
+        id: RSome(target.id()),
+    }
+}
+
+impl From<&EngineConfig> for Engine {
+    fn from(config: &EngineConfig) -> Self {
+        Engine::new(
+            config.name(),
+            Template::new(config.url()),
+            None,
+            config.alias(),
+            config.icon(),
+        )
+    }
+}
+
+trait SearchEngines: common::Browser {
+    fn search_engines(&self) -> Result<Vec<Engine>, Box<dyn Error>>;
+}
+
+// The browser and its engines, which are retried on the next query if they fail to load:
+struct Loaded {
     browser: Box<dyn SearchEngines>,
     engines: Vec<Engine>,
+    bangs: Bangs,
+    groups: Vec<Group>,
 }
 
-#[init]
-fn init(config_dir: RString) -> InitData {
-    let config = common::config(&config_dir, "Websearch");
-    let common_config = common::common_config(&config_dir, "Websearch");
+// Everything that the queries and the handler can change. Queries run at the same time while
+// they wait for suggestions, so they only hold the lock while they use the state:
+struct State {
+    config: common::ConfigFile<Config>,
+    common_config: common::ConfigFile<common::CommonConfig>,
+    loaded: common::Fallible<Loaded>,
+    // How the last query wants its search opened, for the handler, which only gets the match:
+    open_mode: common::OpenMode,
+    // The searches which were selected so far, which are shown again:
+    history: History,
+}
+
+struct InitData {
+    state: Mutex<State>,
+    suggester: Suggester,
+}
 
+// What a query asks the engine for once the state is unlocked again:
+struct SuggestionRequest {
+    url: Template,
+    terms: String,
+    config: SuggestionsConfig,
+    // The match of the typed search, which every suggestion is a copy of:
+    search_match: Match,
+}
+
+// A query that panicked while holding the lock didn't leave anything half-done behind:
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn load(config: &Config, common_config: &common::CommonConfig) -> Result<Loaded, Box<dyn Error>> {
     // NOTE 1
-    let browser_id = common::default_browser_id().unwrap_or_else(|e| {
-        eprintln!("(Websearch) Failed while getting the default browser. Closing...\n  {e}");
-        process::exit(1)
-    });
-    let browser = match browser_id.as_str() {
-        "firefox" => Box::new(common::Firefox::new(common_config.browser_profile_name())),
-        _ => {
-            eprintln!("(Websearch) Unsupported default browser! Closing...");
-            process::exit(1)
-        }
-    };    
+    let known_browser = common::browser(common_config, config.browser())
+        .map_err(|e| format!("Failed while getting the browser:\n    {e}"))?;
+    let browser: Box<dyn SearchEngines> = match known_browser {
+        common::KnownBrowser::Firefox(firefox) => Box::new(firefox),
+        common::KnownBrowser::Chromium(chromium) => Box::new(chromium),
+    };
 
-    let engines = browser
-        .search_engines(common_config.browser_profile_name())
-        .unwrap_or_else(|e| {
-            eprintln!("(Websearch) Failed while getting engines! Closing...\n  {e}");
-            process::exit(1)
-        });
+    let mut engines = browser
+        .search_engines()
+        .map_err(|e| format!("Failed while getting engines:\n    {e}"))?;
+    // The engines from the config come after the browser's, so they don't change which engine
+    // is searched with by default:
+    engines.extend(config.engines().iter().map(Engine::from));
+    let mut bangs = Bangs::load(config.bang_file(), &engines)
+        .map_err(|e| format!("Failed while loading the bangs:\n    {e}"))?;
+    // The icons from the config replace the ones from the browser:
+    for engine in engines.iter_mut().chain(bangs.engines_mut()) {
+        if let Some(icon) = config.icon(&engine.name) {
+            engine.icon = icon.to_string();
+        }
+    }
+    let groups = config
+        .groups()
+        .iter()
+        .map(|group| Group::new(group, &engines, &bangs, config.icon(group.name())))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed while loading the groups:\n    {e}"))?;
 
-    InitData {
-        config,
-        common_config,
+    Ok(Loaded {
         browser,
         engines,
+        bangs,
+        groups,
+    })
+}
+
+#[init]
+fn init(config_dir: RString) -> InitData {
+    let config: common::ConfigFile<Config> = common::config(&config_dir, "Websearch");
+    let common_config = common::common_config(&config_dir, "Websearch");
+    let loaded = common::Fallible::new("Websearch", &config_dir, load(&config, &common_config));
+    // A broken history is started over, rather than keeping the plugin from searching:
+    let history = History::load().unwrap_or_else(|e| {
+        eprintln!("(Websearch) Failed while loading the history:\n  {e}");
+        History::default()
+    });
+
+    InitData {
+        state: Mutex::new(State {
+            config,
+            common_config,
+            loaded,
+            open_mode: common::OpenMode::default(),
+            history,
+        }),
+        suggester: Suggester::new(),
     }
 }
 
@@ -104,16 +290,81 @@ fn info() -> PluginInfo {
 
 #[get_matches]
 fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
-    let InitData {
+    let (mut matches, request, diagnostics) = {
+        let mut state = lock(&data.state);
+        let State {
+            config,
+            common_config,
+            loaded,
+            ..
+        } = &mut *state;
+
+        // Both files are always checked for changes, hence the non-short-circuiting "|":
+        if config.reload() | common_config.reload() {
+            loaded.reload(|| load(config, common_config));
+        }
+
+        let (matches, request) = matches(&input, &mut state);
+        // Problems in the config files are listed after the matches, so they don't go unnoticed:
+        let mut diagnostics = Vec::new();
+        if input.starts_with(state.config.prefix()) {
+            diagnostics.extend(state.config.diagnostic());
+            diagnostics.extend(state.common_config.diagnostic());
+        }
+        (matches, request, diagnostics)
+    };
+
+    // The suggestions are asked for without holding the lock, so newer queries can come in
+    // (and make this one give up) in the meantime:
+    if let Some(request) = request {
+        let suggestions = data
+            .suggester
+            .suggestions(&request.url, &request.terms, &request.config);
+        // Suggestions which are already there (as the typed search or an earlier one) are skipped:
+        let suggestions: Vec<String> = suggestions
+            .into_iter()
+            .filter(|suggestion| {
+                !matches
+                    .iter()
+                    .any(|existing| existing.title.as_str().eq_ignore_ascii_case(suggestion))
+            })
+            .collect();
+        matches.extend(
+            suggestions
+                .into_iter()
+                .take(request.config.max())
+                .map(|suggestion| Match {
+                    title: RString::from(suggestion),
+                    ..request.search_match.clone()
+                }),
+        );
+    }
+    matches.extend(diagnostics);
+    matches
+}
+
+fn matches(input: &str, state: &mut State) -> (RVec<Match>, Option<SuggestionRequest>) {
+    let State {
         config,
-        common_config: _,
-        browser: _,
-        engines,
-    } = data;
+        common_config,
+        loaded,
+        open_mode,
+        history,
+    } = state;
 
     // Early return for wrong prefix:
     if !input.starts_with(config.prefix()) {
-        return RVec::new();
+        return (RVec::new(), None);
+    };
+
+    let Loaded {
+        browser: _,
+        engines,
+        bangs,
+        groups,
+    } = match loaded.get_or_retry(|| load(config, common_config)) {
+        Ok(loaded) => &*loaded,
+        Err(diagnostic) => return (RVec::from(vec![*diagnostic]), None),
     };
 
     // We can safely unwrap here because of the first early return.
@@ -121,43 +372,89 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
 
     // Handling blank input:
     if stripped_input.is_empty() {
-        return RVec::new();
+        return (RVec::new(), None);
+    }
+
+    // A trailing modifier like "!p" isn't searched for, it only changes how the search opens:
+    let (stripped_input, mode) = common::open_mode(stripped_input, common_config, config.browser());
+    *open_mode = mode;
+
+    // A known !bang picks its engine wherever it is in the input, even after an alias:
+    if let Some((index, engine, terms)) = bangs.split(stripped_input) {
+        let target = Target::Bang(index);
+        return (
+            with_history(target, &engine.name, &engine.icon, &terms, history, config),
+            suggestion_request(engine, target, &terms, config),
+        );
+    }
+
+    // A group is picked by its alias, just like an engine. Groups come first, so a group with
+    // the alias "@docs" isn't mistaken for an engine with the alias "@d". The suggestions are
+    // the ones of the first engine:
+    if let Some((index, group)) = groups
+        .iter()
+        .enumerate()
+        .find(|(_, group)| !group.alias.is_empty() && stripped_input.starts_with(&group.alias))
+    {
+        let target = Target::Group(index);
+        let terms = stripped_input.strip_prefix(&group.alias).unwrap().trim();
+        let request = group
+            .engines
+            .first()
+            .and_then(|engine| suggestion_request(engine, target, terms, config))
+            .map(|request| SuggestionRequest {
+                search_match: group.search_match(target, terms),
+                ..request
+            });
+        return (
+            with_history(target, &group.name, &group.icon, terms, history, config),
+            request,
+        );
     }
 
-    
     // Finding the appropriate engine:
-    let valid_engines: Vec<&Engine> = engines
+    let valid_engines: Vec<(usize, &Engine)> = engines
         .iter()
-        .filter(|engine| stripped_input.starts_with(&engine.alias))
+        .enumerate()
+        .filter(|(_, engine)| stripped_input.starts_with(&engine.alias))
         .collect();
-        
-    // I am pretty sure this is necessary in the case that a user doesn't have any engine
-    // with an emptry string alias. I mean think about it, if there is even one empty-string-alias
-    // engine, the `valid_engines` vector will never ever be empty. On the other hand however, if
-    // a user only has non-empty-string-alias engines, there is a chance that `valid_engines` will
-    // be empty. For that reason, the below code is necessary to protect the .unwrap() after the .find_map()
-    // method (see below). I think...
-    if valid_engines.is_empty() {
-        return RVec::new();
-    }
-    
-    // Returning matches for all valid engines which an empty string alias:
-    if valid_engines.iter().all(|engine| engine.alias.is_empty()) {
-        let matches: Vec<Match> = valid_engines
+
+    // Without an alias, the browser's default engine is searched with, followed by every engine
+    // with an empty string alias. If there is neither, there is nothing to search with:
+    if valid_engines
+        .iter()
+        .all(|(_, engine)| engine.alias.is_empty())
+    {
+        let default_engines: Vec<(usize, &Engine)> = engines
             .iter()
-            .map(|engine| {
-                let stripped_input = stripped_input.strip_prefix(&engine.alias).unwrap().trim();
-                let description = format!("Search with {}", &engine.name);
-                Match {
-                    title: RString::from(stripped_input),
-                    description: RSome(RString::from(description)),
-                    use_pango: false,
-                    icon: RSome(RString::from(engine.icon.as_str())),
-                    id: RNone,
-                }
-            })
+            .enumerate()
+            .filter(|(_, engine)| engine.default)
+            .chain(
+                valid_engines
+                    .into_iter()
+                    .filter(|(_, engine)| !engine.default),
+            )
+            .collect();
+        let Some(&(first_index, first)) = default_engines.first() else {
+            return (RVec::new(), None);
+        };
+        let first_target = Target::Engine(first_index);
+        // The earlier searches only come from the first engine, like the suggestions:
+        let matches: Vec<Match> = default_engines
+            .iter()
+            .map(|&(index, engine)| engine.search_match(Target::Engine(index), stripped_input))
+            .chain(earlier_searches(
+                first_target,
+                &first.name,
+                &first.icon,
+                stripped_input,
+                history,
+                config,
+            ))
             .collect();
-        return RVec::from(matches);
+        // Only the first engine is asked for suggestions, the others would mostly suggest the same:
+        let request = suggestion_request(first, first_target, stripped_input, config);
+        return (RVec::from(matches), request);
     }
 
     // Returning the match for a specfic engine. If one engine has the alias "a" and another has the
@@ -166,52 +463,201 @@ fn get_matches(input: RString, data: &InitData) -> RVec<Match> {
     // Well, my reasoning is that if a user is specifying an engine to search with using an alias, it must be that
     // they only intend to search with that engine. That being said, out of all the valid engines only ONE has to
     // show up as a Match. I do not have any intention of making this more complicated than it is, because of that
-    // the below code will return the Match for the first engine that has a non-empty-string alias in the iteration:
-    let matches = valid_engines
+    // the below code will return the Match for the first engine that has a non-empty-string alias in the iteration.
+    // There ain't no way that the .find() method doesn't find a non-empty-string alias, so that's why .unwrap() is here.
+    let &(index, engine) = valid_engines
         .iter()
-        .find_map(|engine| {
-            if engine.alias.is_empty() {
-                return None;
-            }
-            let stripped_input = stripped_input.strip_prefix(&engine.alias).unwrap().trim();
-            let description = format!("Search with {}", &engine.name);
-            Some(vec![Match {
-                title: RString::from(stripped_input),
-                description: RSome(RString::from(description)),
-                use_pango: false,
-                icon: RSome(RString::from(engine.icon.as_str())),
-                id: RNone,
-            }])
-        })
-        // There ain't no way that the .find() method doesn't find a non-empty-string alias, so that's why .unwrap() is here.
+        .find(|(_, engine)| !engine.alias.is_empty())
         .unwrap();
+    let target = Target::Engine(index);
+    let stripped_input = stripped_input.strip_prefix(&engine.alias).unwrap().trim();
+
+    (
+        with_history(
+            target,
+            &engine.name,
+            &engine.icon,
+            stripped_input,
+            history,
+            config,
+        ),
+        suggestion_request(engine, target, stripped_input, config),
+    )
+}
 
+// The typed search followed by the earlier ones. Searching for nothing isn't much use, so
+// without terms only the earlier searches are shown (if there are any):
+fn with_history(
+    target: Target,
+    name: &str,
+    icon: &str,
+    terms: &str,
+    history: &History,
+    config: &Config,
+) -> RVec<Match> {
+    let earlier = earlier_searches(target, name, icon, terms, history, config);
+    let mut matches = Vec::new();
+    if !terms.is_empty() {
+        matches.push(search_match(target, name, icon, terms));
+    }
+    matches.extend(earlier);
     RVec::from(matches)
 }
 
+// The earlier searches with the engine (or group) which match the terms, best first. Without
+// terms, they are all shown, newest first:
+fn earlier_searches(
+    target: Target,
+    name: &str,
+    icon: &str,
+    terms: &str,
+    history: &History,
+    config: &Config,
+) -> Vec<Match> {
+    let history_config = config.history();
+    if !history_config.remembers(name) {
+        return Vec::new();
+    }
+    let mut matcher = history_config.matcher();
+    let mut searches: Vec<(i64, &String)> = history
+        .searches(name)
+        .iter()
+        .filter(|search| !search.eq_ignore_ascii_case(terms))
+        .filter_map(|search| match terms.is_empty() {
+            true => Some((0, search)),
+            false => matcher.score(search, terms).map(|score| (score, search)),
+        })
+        .collect();
+    // The sort is stable, so searches with the same score stay newest first:
+    searches.sort_by_key(|(score, _)| Reverse(*score));
+    searches
+        .into_iter()
+        .take(history_config.max())
+        .map(|(_, search)| search_match(target, name, icon, search))
+        .collect()
+}
+
+// Remembers the selected search, unless the engine is excluded or it was opened in a private
+// window:
+fn remember(
+    history: &mut History,
+    name: &str,
+    terms: &str,
+    open_mode: &common::OpenMode,
+    config: &Config,
+) {
+    let history_config = config.history();
+    if terms.is_empty()
+        || *open_mode == common::OpenMode::Private
+        || !history_config.remembers(name)
+    {
+        return;
+    }
+    history.add(name, terms, history_config.size());
+    history
+        .save()
+        .unwrap_or_else(|e| eprintln!("(Websearch) Failed while saving the history:\n  {e}"));
+}
+
+fn suggestion_request(
+    engine: &Engine,
+    target: Target,
+    terms: &str,
+    config: &Config,
+) -> Option<SuggestionRequest> {
+    let suggestions = config.suggestions();
+    if !suggestions.enabled() || terms.is_empty() {
+        return None;
+    }
+    Some(SuggestionRequest {
+        url: engine.suggestions.clone()?,
+        terms: terms.to_string(),
+        config: suggestions,
+        search_match: engine.search_match(target, terms),
+    })
+}
+
 #[handler]
 fn handler(selection: Match, data: &InitData) -> HandleResult {
-    let InitData {
-        config: _,
+    let mut state = lock(&data.state);
+    let State {
+        config,
         common_config,
+        loaded,
+        open_mode,
+        history,
+    } = &mut *state;
+
+    if let Some(result) = config.handle(&selection, common_config) {
+        return result;
+    }
+    if let Some(result) = common_config.handle(&selection, common_config) {
+        return result;
+    }
+
+    if let Some(result) = loaded.handle(&selection, common_config) {
+        return result;
+    }
+    // It is safe to unwrap here, because only the diagnostic match can be
+    // selected while the engines are failing to load.
+    let Loaded {
         browser,
         engines,
-    } = data;
+        bangs,
+        groups,
+    } = loaded.get().unwrap();
 
-    // The .unwrap() here cannot possibly fail because, well, every selection must have some description.
-    let selected_engine_name = selection.description.unwrap().replace("Search with ", "");
-    // The .unwrap() here cannot fail beacuse the engine vector must hold the element of interest.
-    let engine = engines
-        .iter()
-        .find(|engine| engine.name == selected_engine_name)
-        .unwrap();
+    let target = match selection.id {
+        RSome(id) => Target::from_id(id),
+        RNone => None,
+    };
+    // The group or engine is gone (or another one took its place) if the engines were reloaded
+    // since the match was shown, which is told apart by its name in the description:
+    let is_shown = |name: &str| match &selection.description {
+        RSome(description) => description.as_str() == format!("Search with {name}"),
+        RNone => false,
+    };
 
-    let url = &engine.url.replace("{searchTerms}", &selection.title);
-    browser
-        .new_window(url, common_config.prefix_args())
-        .unwrap_or_else(|e| {
-            eprintln!("(Websearch) Failed while opening a new browser window. Closing...\n  {e}")
-        });
+    match target {
+        // A group opens all of its engines with a single invocation of the browser:
+        Some(Target::Group(index)) => {
+            let Some(group) = groups.get(index).filter(|group| is_shown(&group.name)) else {
+                eprintln!("(Websearch) The selected group doesn't exist anymore. Closing...");
+                return HandleResult::Close;
+            };
+            let urls: Vec<String> = group
+                .engines
+                .iter()
+                .map(|engine| engine.url.expand(&selection.title))
+                .collect();
+            browser
+                .open(&urls, open_mode, group.separate_windows, common_config)
+                .unwrap_or_else(|e| {
+                    eprintln!("(Websearch) Failed while opening the browser. Closing...\n  {e}")
+                });
+            remember(history, &group.name, &selection.title, open_mode, config);
+        }
+        Some(Target::Engine(index)) | Some(Target::Bang(index)) => {
+            let engine = match target {
+                Some(Target::Bang(_)) => bangs.get(index),
+                _ => engines.get(index),
+            };
+            let Some(engine) = engine.filter(|engine| is_shown(&engine.name)) else {
+                eprintln!("(Websearch) The selected engine doesn't exist anymore. Closing...");
+                return HandleResult::Close;
+            };
+            let url = engine.url.expand(&selection.title);
+            browser
+                .open(&[url], open_mode, false, common_config)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "(Websearch) Failed while opening a new browser window. Closing...\n  {e}"
+                    )
+                });
+            remember(history, &engine.name, &selection.title, open_mode, config);
+        }
+        None => eprintln!("(Websearch) Failed while finding the selected engine. Closing..."),
+    }
 
     HandleResult::Close
 }
@@ -0,0 +1,148 @@
+use encoding_rs::{Encoding, UTF_8};
+use std::{borrow::Cow, env};
+
+/// An OpenSearch URL template like "https://duckduckgo.com/?q={searchTerms}", together with
+/// the query parameters which Firefox keeps apart from the template. "{}" is understood as
+/// "{searchTerms}" as well.
+#[derive(Debug, Clone)]
+pub struct Template {
+    template: String,
+    params: Vec<(String, String)>,
+    encoding: &'static Encoding,
+}
+
+impl Template {
+    pub fn new(template: &str) -> Self {
+        Template {
+            template: template.to_string(),
+            params: Vec::new(),
+            encoding: UTF_8,
+        }
+    }
+
+    /// Parameters which are appended to the query, e.g. `("q", "{searchTerms}")`. Their
+    /// values are templates themselves.
+    pub fn params(mut self, params: Vec<(String, String)>) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// The charset the search terms are converted to before they are percent-encoded, e.g.
+    /// "windows-1252". Unknown charsets fall back to UTF-8.
+    pub fn input_encoding(mut self, label: &str) -> Self {
+        self.encoding = Encoding::for_label(label.trim().as_bytes()).unwrap_or_else(|| {
+            eprintln!("(Websearch) Unknown input encoding \"{label}\". Using UTF-8...");
+            UTF_8
+        });
+        self
+    }
+
+    /// The URL which searches for `terms`.
+    pub fn expand(&self, terms: &str) -> String {
+        let mut url = self.expand_part(&self.template, terms, false);
+
+        if !self.params.is_empty() {
+            match url.chars().last() {
+                Some('?' | '&') => (),
+                _ if url.contains('?') => url.push('&'),
+                _ => url.push('?'),
+            }
+            let query: Vec<String> = self
+                .params
+                .iter()
+                .map(|(name, value)| {
+                    format!(
+                        "{}={}",
+                        escape(name.as_bytes(), true),
+                        self.expand_part(value, terms, true)
+                    )
+                })
+                .collect();
+            url.push_str(&query.join("&"));
+        }
+        url
+    }
+
+    // Replaces every "{param}" in `part`. The template itself is already a URL, so only the
+    // values are escaped there, while the literal text of a query parameter is escaped too:
+    fn expand_part(&self, part: &str, terms: &str, is_param: bool) -> String {
+        let mut expanded = String::with_capacity(part.len());
+        let mut rest = part;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+            let literal = &rest[..start];
+            match is_param {
+                true => expanded.push_str(&escape(literal.as_bytes(), true)),
+                false => expanded.push_str(literal),
+            }
+
+            // Spaces are "+" in a query (like Firefox does it) and "%20" anywhere else:
+            let in_query = is_param || expanded.contains('?');
+            let name = rest[start + 1..end].trim_end_matches('?');
+            match name {
+                "searchTerms" | "" => {
+                    let (bytes, _, _) = self.encoding.encode(terms);
+                    expanded.push_str(&escape(&bytes, in_query));
+                }
+                _ => {
+                    // Anything unknown is left out, whether it is optional or not, because
+                    // a literal "{...}" would only break the URL:
+                    let value = self.value(name).unwrap_or_default();
+                    expanded.push_str(&escape(value.as_bytes(), in_query));
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        match is_param {
+            true => expanded.push_str(&escape(rest.as_bytes(), true)),
+            false => expanded.push_str(rest),
+        }
+        expanded
+    }
+
+    // The values of the parameters from the OpenSearch spec and the ones Firefox adds:
+    fn value(&self, name: &str) -> Option<Cow<'static, str>> {
+        let value = match name {
+            "count" => Cow::Borrowed("20"),
+            "startIndex" | "startPage" => Cow::Borrowed("1"),
+            "language" | "moz:locale" => Cow::Owned(locale()),
+            "inputEncoding" => Cow::Borrowed(self.encoding.name()),
+            "outputEncoding" => Cow::Borrowed("UTF-8"),
+            "moz:distributionID" => Cow::Borrowed(""),
+            "moz:official" => Cow::Borrowed("unofficial"),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+// Percent-encodes everything except the unreserved characters from RFC 3986:
+fn escape(bytes: &[u8], space_as_plus: bool) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                escaped.push(byte as char)
+            }
+            b' ' if space_as_plus => escaped.push('+'),
+            _ => escaped.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    escaped
+}
+
+// The user's locale the way OpenSearch wants it, e.g. "en-US" for LANG=en_US.UTF-8:
+fn locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            let language = value.split(['.', '@']).next()?;
+            (!language.is_empty() && language != "C" && language != "POSIX")
+                .then(|| language.replace('_', "-"))
+        })
+        .unwrap_or_else(|| "en-US".to_string())
+}
@@ -0,0 +1,197 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, error::Error};
+
+/// The parts of Firefox's search.json (the decompressed search.json.mozlz4) which the plugin
+/// reads. Its layout changed over the versions, so `parse()` migrates older versions to the
+/// layout of the newest one, the same way Firefox does when it reads an old file.
+#[derive(Debug)]
+pub struct SearchJson {
+    pub engines: Vec<EngineData>,
+    /// The id of the engine which Firefox searches with by default, if it is known.
+    pub default_engine_id: Option<String>,
+}
+
+// The file as it is, before the engines are parsed one by one:
+#[derive(Deserialize)]
+struct RawSearchJson {
+    // Files from before the field was added are version 1:
+    #[serde(default = "first_version")]
+    version: u64,
+    #[serde(default)]
+    engines: Vec<serde_json::Value>,
+    #[serde(rename = "metaData", default)]
+    meta_data: MetaData,
+}
+
+#[derive(Default, Deserialize)]
+struct MetaData {
+    // Up to version 6, the default engine was kept by name:
+    current: Option<String>,
+    #[serde(rename = "defaultEngineId")]
+    default_engine_id: Option<String>,
+    // The default engine of the region, which is used until the user picks another one:
+    #[serde(rename = "appDefaultEngineId")]
+    app_default_engine_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EngineData {
+    // Engines didn't have an id before version 7, `parse()` gives them their name instead:
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "_name")]
+    pub name: String,
+    // Before version 2, this was told by `_loadPath` alone:
+    #[serde(rename = "_isAppProvided")]
+    is_app_provided: Option<bool>,
+    #[serde(rename = "_loadPath")]
+    load_path: Option<String>,
+    // The aliases of the engine itself (e.g. from an add-on or a policy), which were a single
+    // string in some files:
+    #[serde(rename = "_definedAliases", default)]
+    defined_aliases: Aliases,
+    #[serde(rename = "_metaData", default)]
+    pub meta_data: EngineMetaData,
+    #[serde(rename = "_urls", default)]
+    pub urls: Vec<UrlData>,
+    #[serde(rename = "_queryCharset")]
+    pub query_charset: Option<String>,
+    // Older versions keep a single icon, newer ones keep one per size, e.g. under "16" (or
+    // '{"width":16,"height":16}' before that):
+    #[serde(rename = "_iconURL")]
+    icon_url: Option<String>,
+    #[serde(rename = "_iconMapObj")]
+    icon_map: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(untagged)]
+enum Aliases {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EngineMetaData {
+    // The keyword which the user set in the settings:
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UrlData {
+    pub template: String,
+    // The results page doesn't always have a type:
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub params: Vec<ParamData>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParamData {
+    pub name: String,
+    // Params which depend on a pref or on the purpose of the search (like "mozparam"s) don't
+    // have a value, and are left out:
+    pub value: Option<String>,
+}
+
+fn first_version() -> u64 {
+    1
+}
+
+impl SearchJson {
+    /// Parses search.json and migrates it to the newest layout. A broken engine is skipped,
+    /// only a broken file is an error.
+    pub fn parse(json: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let raw: RawSearchJson = serde_json::from_slice(json)?;
+
+        let mut engines = Vec::new();
+        for engine in raw.engines {
+            match serde_json::from_value::<EngineData>(engine) {
+                Ok(mut engine) => {
+                    engine.migrate(raw.version);
+                    engines.push(engine);
+                }
+                Err(e) => eprintln!("(Websearch) Skipping an engine in search.json:\n  {e}"),
+            }
+        }
+
+        // Version 7 replaced the name of the default engine with its id. An empty id means
+        // that the user never picked another engine than the one of the region:
+        let MetaData {
+            current,
+            default_engine_id,
+            app_default_engine_id,
+        } = raw.meta_data;
+        let default_engine_id = match (default_engine_id, current) {
+            (Some(id), _) if raw.version >= 7 && !id.is_empty() => Some(id),
+            (_, Some(name)) if raw.version < 7 && !name.is_empty() => engines
+                .iter()
+                .find(|engine| engine.name == name)
+                .map(|engine| engine.id.clone()),
+            _ => app_default_engine_id.filter(|id| !id.is_empty()),
+        };
+
+        Ok(SearchJson {
+            engines,
+            default_engine_id,
+        })
+    }
+}
+
+impl EngineData {
+    // Versions newer than the ones below are read like the newest one:
+    fn migrate(&mut self, version: u64) {
+        if version < 7 || self.id.is_empty() {
+            self.id = self.name.clone();
+        }
+        if version < 2 || self.is_app_provided.is_none() {
+            let load_path = self.load_path.as_deref().unwrap_or_default();
+            self.is_app_provided = Some(load_path.starts_with("[app]"));
+        }
+    }
+
+    /// The keyword of the engine: the one from the settings if there is one, or else the
+    /// first one the engine defines.
+    pub fn alias(&self) -> Option<&str> {
+        let defined = match &self.defined_aliases {
+            Aliases::None => None,
+            Aliases::One(alias) => Some(alias.as_str()),
+            Aliases::Many(aliases) => aliases.first().map(String::as_str),
+        };
+        self.meta_data
+            .alias
+            .as_deref()
+            .or(defined)
+            .filter(|alias| !alias.is_empty())
+    }
+
+    /// The icon of the engine, which is usually a data URI. The biggest one is picked when
+    /// there are several sizes.
+    pub fn icon(&self) -> Option<&str> {
+        let biggest = self
+            .icon_map
+            .iter()
+            .flatten()
+            .max_by_key(|(size, _)| {
+                let size = size.trim_start_matches(|c: char| !c.is_ascii_digit());
+                let end = size
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(size.len());
+                size[..end].parse::<u64>().unwrap_or(0)
+            })
+            .map(|(_, icon)| icon.as_str());
+        self.icon_url
+            .as_deref()
+            .or(biggest)
+            .filter(|icon| !icon.is_empty())
+    }
+
+    pub fn is_app_provided(&self) -> bool {
+        self.is_app_provided.unwrap_or(false)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{config::SuggestionsConfig, lock, opensearch::Template};
+
+// After an engine couldn't be reached (e.g. while offline), it isn't asked for this long, so
+// typing doesn't wait for the timeout on every key. The other engines are still asked:
+const BACKOFF: Duration = Duration::from_secs(30);
+// The cache is simply emptied once it holds this many answers:
+const CACHE_SIZE: usize = 128;
+
+/// Asks the engines' OpenSearch suggestion URLs for suggestions. It is shared by all queries,
+/// which run at the same time, so a query which is superseded while it waits out the delay
+/// doesn't ask the engine at all.
+pub struct Suggester {
+    agent: ureq::Agent,
+    // Every query bumps this, so a waiting query knows when a newer one came in:
+    generation: AtomicU64,
+    // Answers by URL, so going back to an earlier input doesn't ask again:
+    cache: Mutex<HashMap<String, Vec<String>>>,
+    // When each engine last failed, by its URL without terms:
+    failed_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl Suggester {
+    pub fn new() -> Self {
+        Suggester {
+            agent: ureq::AgentBuilder::new().build(),
+            generation: AtomicU64::new(0),
+            cache: Mutex::new(HashMap::new()),
+            failed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The suggestions for `terms`, or none if they are turned off, a newer query came in
+    /// during the delay or the engine didn't answer in time.
+    pub fn suggestions(
+        &self,
+        template: &Template,
+        terms: &str,
+        config: &SuggestionsConfig,
+    ) -> Vec<String> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if !config.enabled() || config.max() == 0 {
+            return Vec::new();
+        }
+
+        let url = template.expand(terms);
+        if let Some(suggestions) = lock(&self.cache).get(&url) {
+            return suggestions.clone();
+        }
+        let engine = template.expand("");
+        if lock(&self.failed_at)
+            .get(&engine)
+            .is_some_and(|failed_at| failed_at.elapsed() < BACKOFF)
+        {
+            return Vec::new();
+        }
+
+        thread::sleep(config.delay());
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return Vec::new();
+        }
+
+        match self.fetch(&url, config.timeout()) {
+            Ok(suggestions) => {
+                let mut cache = lock(&self.cache);
+                if cache.len() >= CACHE_SIZE {
+                    cache.clear();
+                }
+                cache.insert(url, suggestions.clone());
+                suggestions
+            }
+            Err(e) => {
+                eprintln!("(Websearch) Failed while getting suggestions:\n  {e}");
+                lock(&self.failed_at).insert(engine, Instant::now());
+                Vec::new()
+            }
+        }
+    }
+
+    fn fetch(&self, url: &str, timeout: Duration) -> Result<Vec<String>, Box<dyn Error>> {
+        let body = self
+            .agent
+            .get(url)
+            .timeout(timeout)
+            .call()
+            .map_err(|e| format!("Failed while requesting {url}:\n    {e}"))?
+            .into_string()
+            .map_err(|e| format!("Failed while reading the answer of {url}:\n    {e}"))?;
+        parse(&body)
+            .map_err(|e| format!("Failed while parsing the answer of {url}:\n    {e}").into())
+    }
+}
+
+// The answer looks like ["<terms>", ["<suggestion>", ...], ...], where everything after the
+// suggestions (descriptions and URLs) is optional and ignored:
+fn parse(body: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let data: serde_json::Value = serde_json::from_str(body)?;
+    let suggestions = data
+        .get(1)
+        .and_then(|suggestions| suggestions.as_array())
+        .ok_or("The answer doesn't have a list of suggestions!")?
+        .iter()
+        .filter_map(|suggestion| suggestion.as_str())
+        .map(str::to_string)
+        .collect();
+    Ok(suggestions)
+}
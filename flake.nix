@@ -96,6 +96,13 @@
         src = fileSetForCrate ./crates;
       });
 
+      # Prints the effective config of every plugin, their JSON Schemas and templates:
+      config-check = craneLib.buildPackage (individualCrateArgs // {
+        pname = "config-check";
+        cargoExtraArgs = "-p config-check";
+        src = fileSetForCrate ./crates;
+      });
+
       # TESTING
       mkAnyrunConfig = pkgs.writeText "config.ron" ''
         Config(
@@ -205,6 +212,7 @@
           ${pkgs.anyrun}/bin/anyrun -c ${anyrunConfigDir}
           sleep 2.5
         '';
+        inherit config-check;
         default = plugins;
       };
 
@@ -218,6 +226,7 @@
         # Extra inputs can be added here; cargo and rustc are provided by default.
         packages = [
           pkgs.cargo-hakari
+          pkgs.dbus
           pkgs.rust-analyzer
         ];
 